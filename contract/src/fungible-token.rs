@@ -20,7 +20,7 @@ use near_contract_standards::fungible_token::metadata::{
 };
 use near_contract_standards::fungible_token::FungibleToken;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LazyOption;
+use near_sdk::collections::{LazyOption, UnorderedSet};
 use near_sdk::json_types::U128;
 use near_sdk::{
     env, log, near_bindgen, require, AccountId, Balance, BorshStorageKey, PanicOnDefault,
@@ -33,6 +33,7 @@ pub struct Contract {
     token: FungibleToken,
     metadata: LazyOption<FungibleTokenMetadata>,
     owner_id: AccountId,
+    minters: UnorderedSet<AccountId>,
 }
 
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 288 288'%3E%3Cg id='l' data-name='l'%3E%3Cpath d='M187.58,79.81l-30.1,44.69a3.2,3.2,0,0,0,4.75,4.2L191.86,103a1.2,1.2,0,0,1,2,.91v80.46a1.2,1.2,0,0,1-2.12.77L102.18,77.93A15.35,15.35,0,0,0,90.47,72.5H87.34A15.34,15.34,0,0,0,72,87.84V201.16A15.34,15.34,0,0,0,87.34,216.5h0a15.35,15.35,0,0,0,13.08-7.31l30.1-44.69a3.2,3.2,0,0,0-4.75-4.2L96.14,186a1.2,1.2,0,0,1-2-.91V104.61a1.2,1.2,0,0,1,2.12-.77l89.55,107.23a15.35,15.35,0,0,0,11.71,5.43h3.13A15.34,15.34,0,0,0,216,201.16V87.84A15.34,15.34,0,0,0,200.66,72.5h0A15.35,15.35,0,0,0,187.58,79.81Z'/%3E%3C/g%3E%3C/svg%3E";
@@ -41,6 +42,7 @@ const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/svg+xml,%3Csvg xmlns='http://
 enum StorageKey {
     FungibleToken,
     Metadata,
+    Minters,
 }
 
 #[near_bindgen]
@@ -74,26 +76,51 @@ impl Contract {
             token: FungibleToken::new(StorageKey::FungibleToken),
             metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
             owner_id: owner_contract_id,
+            minters: UnorderedSet::new(StorageKey::Minters),
         };
+        this.minters.insert(&this.owner_id);
         this.token.internal_register_account(&this.owner_id);
         this.token.internal_deposit(&this.owner_id, total_supply.into());
         this
     }
 
     pub fn mint_tokens(&mut self, account_id: AccountId, amount: U128) {
-        self.assert_owner_or_market_calling();
+        self.assert_minter_calling();
         self.token.internal_deposit(&account_id, amount.into());
     }
     
     pub fn burn_tokens(&mut self, account_id: AccountId, amount: U128) {
-        self.assert_owner_or_market_calling();
+        self.assert_minter_calling();
         self.token.internal_withdraw(&account_id, amount.into());
     }
     
-    fn assert_owner_or_market_calling(&self) {
+    /// Allows `account_id` to call `mint_tokens` and `burn_tokens`.
+    pub fn add_minter(&mut self, account_id: AccountId) {
+        self.assert_owner_calling();
+        self.minters.insert(&account_id);
+    }
+
+    pub fn remove_minter(&mut self, account_id: AccountId) {
+        self.assert_owner_calling();
+        self.minters.remove(&account_id);
+    }
+
+    pub fn get_minters(&self) -> Vec<AccountId> {
+        self.minters.to_vec()
+    }
+
+    fn assert_owner_calling(&self) {
+        assert_eq!(
+            &env::predecessor_account_id(),
+            &self.owner_id,
+            "Only the owner can call this function."
+        );
+    }
+
+    fn assert_minter_calling(&self) {
         assert!(
-            &env::predecessor_account_id() == &self.owner_id || &env::predecessor_account_id() == "market.contract", // replace "market.contract" with the account id of your market contract
-            "Only the owner or the market contract can call this function."
+            self.minters.contains(&env::predecessor_account_id()),
+            "Only a minter can call this function."
         );
     }
     
@@ -0,0 +1,449 @@
+//! OMT-weighted governance over protocol parameters.
+//!
+//! An account's voting power is all the OMT it owns: its balance plus
+//! anything it has staked or is unstaking. Creating a proposal takes a
+//! snapshot, and votes on it are weighed with each voter's power as of that
+//! snapshot. Power is checkpointed lazily: the first time an account's power
+//! is about to change after a snapshot, its old value is recorded under that
+//! snapshot's id.
+use super::*;
+
+/// Voting period used until the owner changes it: 3 days.
+pub const DEFAULT_VOTING_PERIOD: u64 = 3 * 24 * 60 * 60 * 1_000_000_000;
+/// Quorum used until the owner changes it: 10% of the supply.
+pub const DEFAULT_QUORUM_BPS: u16 = 1_000;
+/// Voting power needed to create a proposal until the owner changes it:
+/// 1,000 OMT.
+pub const DEFAULT_PROPOSAL_THRESHOLD: u128 = 1_000 * 10u128.pow(24);
+
+const BPS_DENOMINATOR: u128 = 10_000;
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct GovernanceConfig {
+    /// How long a proposal is open for voting, in nanoseconds.
+    voting_period: U64,
+    /// Share of the OMT supply at the snapshot that must vote, in basis points.
+    quorum_bps: u16,
+    /// Voting power needed to create a proposal. Must be nonzero, so that
+    /// accounts without OMT cannot spam proposals.
+    proposal_threshold: U128,
+}
+
+impl Default for GovernanceConfig {
+    fn default() -> Self {
+        Self {
+            voting_period: DEFAULT_VOTING_PERIOD.into(),
+            quorum_bps: DEFAULT_QUORUM_BPS,
+            proposal_threshold: DEFAULT_PROPOSAL_THRESHOLD.into(),
+        }
+    }
+}
+
+/// A protocol parameter change a proposal applies when it passes.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub enum ParameterChange {
+    FeeBps(u16),
+    MinOfferAmount(U128),
+    AllowTokenContract(AccountId),
+    DisallowTokenContract(AccountId),
+    AddResolver(AccountId),
+    RemoveResolver(AccountId),
+}
+
+impl ParameterChange {
+    /// Checked when a proposal is created, so that applying the change once
+    /// it passes cannot fail.
+    pub fn assert_valid(&self) {
+        if let Self::FeeBps(fee_bps) = self {
+            require!((*fee_bps as u128) < FEE_DENOMINATOR, "Fee must be below 100%.");
+        }
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum ProposalStatus {
+    Active,
+    Executed,
+    Rejected,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Proposal {
+    id: u32,
+    proposer: AccountId,
+    description: String,
+    change: ParameterChange,
+    /// Id of the voting power snapshot taken at creation.
+    snapshot: u64,
+    /// OMT supply at creation, used for the quorum.
+    total_supply: U128,
+    voting_ends_at: U64,
+    votes_for: U128,
+    votes_against: U128,
+    status: ProposalStatus,
+}
+
+impl Contract {
+    /// All the OMT `account_id` owns right now.
+    pub(crate) fn voting_power(&self, account_id: &AccountId) -> u128 {
+        let stake = self
+            .stakes
+            .get(account_id)
+            .map_or(0, |s| s.staked.0 + s.unstaking.0);
+        Self::balance_of(account_id) + stake
+    }
+
+    /// Voting power of `account_id` as of `snapshot`.
+    fn voting_power_at(&self, account_id: &AccountId, snapshot: u64) -> u128 {
+        self.power_checkpoints
+            .get(account_id)
+            .and_then(|checkpoints| {
+                // Binary search for the first checkpoint taken at or after
+                // `snapshot`.
+                let (mut low, mut high) = (0, checkpoints.len());
+                while low < high {
+                    let mid = low + (high - low) / 2;
+                    if checkpoints[mid].0 < snapshot {
+                        low = mid + 1;
+                    } else {
+                        high = mid;
+                    }
+                }
+                checkpoints.get(low)
+            })
+            .map_or_else(|| self.voting_power(account_id), |(_, power)| *power)
+    }
+
+    /// Records the voting power of `account_id` under the latest snapshot if
+    /// that has not been done yet. Must be called before every change to an
+    /// account's voting power.
+    pub(crate) fn checkpoint_voting_power(&mut self, account_id: &AccountId) {
+        let snapshot = self.snapshot_id;
+        if snapshot == 0 {
+            return;
+        }
+
+        let power = self.voting_power(account_id);
+        let checkpoints = self
+            .power_checkpoints
+            .entry(account_id.clone())
+            .or_insert_with(|| {
+                Vector::new(StorageKey::AccountPowerCheckpoints(account_id.clone()))
+            });
+        let last = checkpoints.len().checked_sub(1);
+        if last.map_or(true, |i| checkpoints[i].0 < snapshot) {
+            checkpoints.push((snapshot, power));
+        }
+    }
+
+    fn apply_parameter_change(&mut self, change: &ParameterChange) {
+        match change {
+            ParameterChange::FeeBps(fee_bps) => {
+                self.fee_bps = *fee_bps;
+
+                ContractEvent::FeeUpdated {
+                    fee_bps: *fee_bps,
+                    treasury: self.treasury.clone(),
+                }
+                .emit();
+            }
+            ParameterChange::MinOfferAmount(amount) => {
+                self.min_offer_amount = amount.0;
+            }
+            ParameterChange::AllowTokenContract(account_id) => {
+                self.allowed_token_contracts.insert(account_id.clone());
+            }
+            ParameterChange::DisallowTokenContract(account_id) => {
+                self.allowed_token_contracts.remove(account_id);
+            }
+            ParameterChange::AddResolver(account_id) => {
+                self.add_role(account_id.clone(), &Role::Resolver);
+
+                ContractEvent::RoleGranted {
+                    account_id: account_id.clone(),
+                    role: Role::Resolver,
+                }
+                .emit();
+            }
+            ParameterChange::RemoveResolver(account_id) => {
+                self.remove_role(account_id, &Role::Resolver);
+
+                ContractEvent::RoleRevoked {
+                    account_id: account_id.clone(),
+                    role: Role::Resolver,
+                }
+                .emit();
+            }
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Opens a proposal and snapshots voting power for it.
+    pub fn create_proposal(&mut self, description: String, change: ParameterChange) -> Proposal {
+        let proposer = env::predecessor_account_id();
+        require!(
+            self.voting_power(&proposer) >= self.governance_config.proposal_threshold.0,
+            "You do not have enough voting power to create a proposal."
+        );
+        require!(
+            description.len() <= MAX_DESCRIPTION_LEN,
+            "Description is too long."
+        );
+        change.assert_valid();
+
+        self.snapshot_id += 1;
+        let proposal = Proposal {
+            id: self.proposals.len(),
+            proposer: proposer.clone(),
+            description,
+            change,
+            snapshot: self.snapshot_id,
+            total_supply: Self::total_supply().into(),
+            voting_ends_at: (env::block_timestamp() + self.governance_config.voting_period.0)
+                .into(),
+            votes_for: 0.into(),
+            votes_against: 0.into(),
+            status: ProposalStatus::Active,
+        };
+        self.proposals.push(proposal.clone());
+
+        ContractEvent::ProposalCreated {
+            proposal_id: proposal.id,
+            proposer,
+        }
+        .emit();
+
+        proposal
+    }
+
+    /// Votes on an active proposal with the caller's power at its snapshot.
+    pub fn vote(&mut self, proposal_id: u32, approve: bool) {
+        let account_id = env::predecessor_account_id();
+        let proposal = self
+            .proposals
+            .get(proposal_id)
+            .unwrap_or_else(|| env::panic_str("Proposal does not exist!"));
+        require!(
+            proposal.status == ProposalStatus::Active
+                && env::block_timestamp() < proposal.voting_ends_at.0,
+            "Voting on this proposal has ended."
+        );
+        let weight = self.voting_power_at(&account_id, proposal.snapshot);
+        require!(weight > 0, "You had no voting power when this proposal was created.");
+        require!(
+            self.votes
+                .insert((proposal_id, account_id.clone()), approve)
+                .is_none(),
+            "You have already voted on this proposal."
+        );
+
+        let proposal = self.proposals.get_mut(proposal_id).unwrap();
+        if approve {
+            proposal.votes_for.0 += weight;
+        } else {
+            proposal.votes_against.0 += weight;
+        }
+
+        ContractEvent::VoteCast {
+            proposal_id,
+            account_id,
+            approve,
+            weight: weight.into(),
+        }
+        .emit();
+    }
+
+    /// Closes a proposal whose voting period is over, applying its change if
+    /// it reached quorum and a majority. Callable by anyone.
+    pub fn execute_proposal(&mut self, proposal_id: u32) -> ProposalStatus {
+        let proposal = self
+            .proposals
+            .get_mut(proposal_id)
+            .unwrap_or_else(|| env::panic_str("Proposal does not exist!"));
+        require!(
+            proposal.status == ProposalStatus::Active,
+            "Proposal has already been closed."
+        );
+        require!(
+            env::block_timestamp() >= proposal.voting_ends_at.0,
+            "Voting on this proposal has not ended yet."
+        );
+
+        let turnout = proposal.votes_for.0 + proposal.votes_against.0;
+        let quorum = proposal.total_supply.0 * self.governance_config.quorum_bps as u128
+            / BPS_DENOMINATOR;
+        let passed = turnout >= quorum && proposal.votes_for.0 > proposal.votes_against.0;
+        proposal.status = if passed {
+            ProposalStatus::Executed
+        } else {
+            ProposalStatus::Rejected
+        };
+        let status = proposal.status;
+        let change = proposal.change.clone();
+
+        if passed {
+            self.apply_parameter_change(&change);
+        }
+
+        ContractEvent::ProposalClosed {
+            proposal_id,
+            status,
+        }
+        .emit();
+
+        status
+    }
+
+    pub fn get_proposal(&self, proposal_id: u32) -> Option<&Proposal> {
+        self.proposals.get(proposal_id)
+    }
+
+    pub fn list_proposals(&self, from_index: Option<u32>, limit: Option<u32>) -> Vec<&Proposal> {
+        paginate(self.proposals.iter(), from_index, limit).collect()
+    }
+
+    pub fn get_vote(&self, proposal_id: u32, account_id: AccountId) -> Option<bool> {
+        self.votes.get(&(proposal_id, account_id)).copied()
+    }
+
+    pub fn get_voting_power(&self, account_id: AccountId) -> U128 {
+        self.voting_power(&account_id).into()
+    }
+
+    pub fn get_governance_config(&self) -> &GovernanceConfig {
+        &self.governance_config
+    }
+
+    /// Owner only.
+    pub fn set_governance_config(&mut self, config: GovernanceConfig) {
+        Self::require_owner();
+        require!(
+            (config.quorum_bps as u128) <= BPS_DENOMINATOR,
+            "Quorum cannot exceed 100%."
+        );
+        require!(
+            config.proposal_threshold.0 > 0,
+            "Proposal threshold must be nonzero."
+        );
+        self.governance_config = config;
+    }
+
+    pub fn get_min_offer_amount(&self) -> U128 {
+        self.min_offer_amount.into()
+    }
+
+    /// Token contracts markets may be created with. Empty means any.
+    pub fn get_allowed_token_contracts(&self) -> Vec<&AccountId> {
+        self.allowed_token_contracts.iter().collect()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    const OMT: Balance = 10u128.pow(24);
+
+    fn context(predecessor: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .predecessor_account_id(predecessor);
+        builder
+    }
+
+    /// `accounts(1)` owns 2,000 of the 10,000 OMT in existence and
+    /// `accounts(2)` the rest.
+    fn setup() -> Contract {
+        testing_env!(context(accounts(0)).build());
+        let mut contract = Contract::new(None);
+        contract.mint(accounts(1), 2_000 * OMT, None);
+        contract.mint(accounts(2), 8_000 * OMT, None);
+        contract
+    }
+
+    fn propose_fee(contract: &mut Contract, fee_bps: u16) -> u32 {
+        testing_env!(context(accounts(1)).build());
+        contract
+            .create_proposal(
+                "Raise the fee".to_string(),
+                ParameterChange::FeeBps(fee_bps),
+            )
+            .id
+    }
+
+    fn execute(contract: &mut Contract, proposal_id: u32) -> ProposalStatus {
+        testing_env!(context(accounts(3))
+            .block_timestamp(DEFAULT_VOTING_PERIOD)
+            .build());
+        contract.execute_proposal(proposal_id)
+    }
+
+    #[test]
+    fn votes_weighed_at_snapshot() {
+        let mut contract = setup();
+        let proposal_id = propose_fee(&mut contract, 100);
+
+        testing_env!(context(accounts(1)).attached_deposit(1).build());
+        contract.ft_transfer(accounts(3), (1_500 * OMT).into(), None);
+        testing_env!(context(accounts(1)).build());
+        contract.vote(proposal_id, true);
+
+        let proposal = contract.get_proposal(proposal_id).unwrap();
+        assert_eq!(proposal.votes_for.0, 2_000 * OMT);
+        assert_eq!(contract.get_voting_power(accounts(1)).0, 500 * OMT);
+
+        assert_eq!(
+            execute(&mut contract, proposal_id),
+            ProposalStatus::Executed
+        );
+        assert_eq!(contract.get_fee().0, 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "You had no voting power when this proposal was created.")]
+    fn tokens_received_after_snapshot_do_not_vote() {
+        let mut contract = setup();
+        let proposal_id = propose_fee(&mut contract, 100);
+
+        testing_env!(context(accounts(1)).attached_deposit(1).build());
+        contract.ft_transfer(accounts(3), (1_500 * OMT).into(), None);
+
+        testing_env!(context(accounts(3)).build());
+        contract.vote(proposal_id, true);
+    }
+
+    #[test]
+    fn proposal_without_quorum_rejected() {
+        let mut contract = setup();
+        contract.set_governance_config(GovernanceConfig {
+            quorum_bps: 5_000,
+            ..GovernanceConfig::default()
+        });
+        let proposal_id = propose_fee(&mut contract, 100);
+
+        contract.vote(proposal_id, true);
+
+        assert_eq!(
+            execute(&mut contract, proposal_id),
+            ProposalStatus::Rejected
+        );
+        assert_eq!(contract.get_fee().0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Fee must be below 100%.")]
+    fn invalid_fee_not_proposed() {
+        let mut contract = setup();
+        propose_fee(&mut contract, 10_000);
+    }
+}
@@ -1,22 +1,80 @@
+use near_contract_standards::fungible_token::metadata::{
+    FungibleTokenMetadata, FT_METADATA_SPEC,
+};
 use near_sdk::{
     borsh::{self, BorshDeserialize, BorshSerialize},
     env,
-    json_types::U128,
+    json_types::{Base64VecU8, U128, U64},
     near_bindgen, require,
     serde::{Deserialize, Serialize},
+    serde_json::json,
     store::*,
-    AccountId, BorshStorageKey, PanicOnDefault, Promise,
+    AccountId, Balance, BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseError,
 };
 use near_sdk_contract_tools::{
     event,
+    owner::Owner,
+    pause::Pause,
+    rbac::Rbac,
     standard::{
-        nep141::{Nep141Hook, Nep141Resolver, Nep141},
+        nep141::{Nep141Controller, Nep141Hook, Nep141Resolver, Nep141},
         nep297::Event,
     },
-    FungibleToken,
+    FungibleToken, Owner, Pause, Rbac,
 };
 
+mod governance;
+mod migrate;
+mod rewards;
+mod staking;
+mod u256 {
+    // The macro expands to code clippy objects to.
+    #![allow(clippy::all)]
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+}
+
+use governance::{GovernanceConfig, ProposalStatus, Proposal};
+use rewards::{EpochRewards, MakerRewards, RewardConfig};
+use staking::{DiscountTier, StakeInfo};
+
+/// Page size used by list views when no `limit` is given.
+pub const DEFAULT_PAGE_LIMIT: u32 = 50;
+/// Upper bound on `limit` for list views, to stay within view gas.
+pub const MAX_PAGE_LIMIT: u32 = 100;
+/// Number of most recent trades kept per market.
+pub const TRADE_HISTORY_LEN: u32 = 256;
+/// Prices are expressed in basis points of the long side's implied probability.
+pub const PRICE_DENOMINATOR: u128 = 10_000;
+/// Fees are expressed in basis points of the payout.
+pub const FEE_DENOMINATOR: u128 = 10_000;
+
+pub const MAX_DESCRIPTION_LEN: usize = 280;
+pub const MAX_CATEGORY_LEN: usize = 32;
+pub const MAX_TAGS: usize = 8;
+pub const MAX_TAG_LEN: usize = 32;
+pub const MAX_RULES_LEN: usize = 4096;
+pub const MAX_URL_LEN: usize = 512;
+pub const MAX_OUTCOME_LEN: usize = 32;
+
+/// How long unstaked OMT stays locked unless the owner changes it: 7 days.
+pub const DEFAULT_UNSTAKE_COOLDOWN: u64 = 7 * 24 * 60 * 60 * 1_000_000_000;
+
 const TOKEN_CONTRACT_WASM: &[u8] = include_bytes!("../../target/wasm32-unknown-unknown/release/token_contract.wasm");
+const GAS_FOR_TOKEN_INIT: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_ON_OUTCOME_TOKEN_CREATED: Gas = Gas(5_000_000_000_000);
+/// Storage an outcome token needs on top of its code: its own state and
+/// metadata, and the first few registered accounts.
+const OUTCOME_TOKEN_STATE_BYTES: u64 = 10_000;
+const GAS_FOR_TOKEN_FREEZE: Gas = Gas(5_000_000_000_000);
+const GAS_FOR_TOKEN_OWNERSHIP: Gas = Gas(5_000_000_000_000);
+const GAS_FOR_TOKEN_MINT: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_ON_OUTCOME_TOKENS_MINTED: Gas = Gas(10_000_000_000_000);
+/// Deposit that registers an account with an outcome token, paid by each
+/// side of a match on markets with outcome tokens. Whatever the token does not
+/// use is credited back.
+pub const OUTCOME_TOKEN_REGISTRATION: Balance = 1_250_000_000_000_000_000_000;
 
 #[event(
     standard = "x-predictions-market",
@@ -44,6 +102,18 @@ enum ContractEvent {
     MarketClosed {
         market_id: u32,
     },
+    MarketInvalidated {
+        market_id: u32,
+    },
+    BondSlashed {
+        market_id: u32,
+        amount: U128,
+    },
+    TokensRedeemed {
+        market_id: u32,
+        account_id: AccountId,
+        amount: U128,
+    },
     Credits {
         account_id: AccountId,
         amount: U128,
@@ -52,6 +122,79 @@ enum ContractEvent {
         account_id: AccountId,
         amount: U128,
     },
+    OperationPaused {
+        operation: Operation,
+    },
+    OperationUnpaused {
+        operation: Operation,
+    },
+    RoleGranted {
+        account_id: AccountId,
+        role: Role,
+    },
+    RoleRevoked {
+        account_id: AccountId,
+        role: Role,
+    },
+    FeeUpdated {
+        fee_bps: u16,
+        treasury: AccountId,
+    },
+    RewardsClaimed {
+        account_id: AccountId,
+        amount: U128,
+    },
+    Staked {
+        account_id: AccountId,
+        amount: U128,
+    },
+    Unstaked {
+        account_id: AccountId,
+        amount: U128,
+    },
+    ProposalCreated {
+        proposal_id: u32,
+        proposer: AccountId,
+    },
+    VoteCast {
+        proposal_id: u32,
+        account_id: AccountId,
+        approve: bool,
+        weight: U128,
+    },
+    ProposalClosed {
+        proposal_id: u32,
+        status: ProposalStatus,
+    },
+}
+
+#[derive(
+    BorshSerialize, BorshStorageKey, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub enum Role {
+    /// May create markets while open market creation is disabled.
+    MarketCreator,
+    /// May resolve any market, not only ones they created.
+    Resolver,
+    /// May invalidate markets.
+    Moderator,
+    /// May change the fee rate and the treasury account.
+    FeeManager,
+}
+
+/// Entrypoints that can be paused independently of each other.
+#[derive(
+    BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq,
+    PartialOrd, Ord,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum Operation {
+    CreateMarket,
+    CreateOffer,
+    AcceptOffer,
+    Withdraw,
+    ClaimRewards,
 }
 
 
@@ -62,7 +205,261 @@ pub struct Market {
     description: String,
     owner: AccountId,
     shares: Vector<SharePair>,
-    token_contract: AccountId,  // add this field
+    offers: UnorderedSet<u32>,
+    token_contract: AccountId,
+    created_at: u64,
+    outcome: Option<bool>,
+    stats: MarketStats,
+    participants: LookupSet<AccountId>,
+    trades: Vector<Trade>,
+    trade_count: u64,
+    /// Creator bond still held for this market.
+    bond: U128,
+    /// The creator must resolve the market before this time to get the bond back.
+    resolution_deadline: u64,
+    metadata: MarketMetadata,
+    /// Whether matches mint outcome tokens. Markets migrated from before
+    /// outcome tokens existed settle their shares directly instead.
+    outcome_tokens: bool,
+    /// Outcome tokens that could not be minted, paid out when the market closes.
+    unminted: Vector<Position>,
+}
+
+/// Descriptive information about a market, supplied at creation.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MarketMetadata {
+    category: String,
+    tags: Vec<String>,
+    /// Detailed rules the resolver follows when closing the market.
+    rules: String,
+    /// URL of the source the outcome is determined from.
+    resolution_source: Option<String>,
+    /// URL of an image or an off-chain JSON file with more information.
+    reference: Option<String>,
+    /// Base64-encoded sha256 hash of the content of `reference`.
+    reference_hash: Option<Base64VecU8>,
+    /// Time at which the event the market is about is expected to happen.
+    end_date: U64,
+}
+
+impl MarketMetadata {
+    pub fn assert_valid(&self) {
+        require!(
+            !self.category.is_empty() && self.category.len() <= MAX_CATEGORY_LEN,
+            "Category must be between 1 and 32 bytes."
+        );
+        require!(self.tags.len() <= MAX_TAGS, "Too many tags.");
+        for (i, tag) in self.tags.iter().enumerate() {
+            require!(
+                !tag.is_empty() && tag.len() <= MAX_TAG_LEN,
+                "Tags must be between 1 and 32 bytes."
+            );
+            require!(!self.tags[..i].contains(tag), "Duplicate tag.");
+        }
+        require!(self.rules.len() <= MAX_RULES_LEN, "Rules are too long.");
+        require!(
+            self.resolution_source
+                .as_ref()
+                .map_or(true, |s| s.len() <= MAX_URL_LEN),
+            "Resolution source is too long."
+        );
+        require!(
+            self.reference
+                .as_ref()
+                .map_or(true, |s| s.len() <= MAX_URL_LEN),
+            "Reference is too long."
+        );
+        require!(
+            self.reference.is_some() == self.reference_hash.is_some(),
+            "Reference and reference hash must be given together."
+        );
+        if let Some(reference_hash) = &self.reference_hash {
+            require!(reference_hash.0.len() == 32, "Hash has to be 32 bytes");
+        }
+    }
+}
+
+/// Account of the token for outcome `index` of market `market_id`. Outcome 0 is the long side
+/// and outcome 1 the short side.
+fn outcome_token_account(market_id: u32, index: usize) -> AccountId {
+    format!("{}-{}.{}", market_id, index, env::current_account_id())
+        .parse()
+        .unwrap()
+}
+
+/// Balance each outcome token account is created with, enough to stake the
+/// storage of its code and initial state.
+fn outcome_token_deposit() -> Balance {
+    (TOKEN_CONTRACT_WASM.len() as u64 + OUTCOME_TOKEN_STATE_BYTES) as Balance
+        * env::storage_byte_cost()
+}
+
+/// Inverse of [`outcome_token_account`]: the market and outcome `account_id` is the token of.
+fn parse_outcome_token_account(account_id: &AccountId) -> Option<(u32, usize)> {
+    let suffix = format!(".{}", env::current_account_id());
+    let (market_id, index) = account_id
+        .as_str()
+        .strip_suffix(&suffix)?
+        .split_once('-')?;
+    Some((market_id.parse().ok()?, index.parse().ok()?))
+}
+
+/// Metadata of the token for `outcome` of market `market_id`, e.g. "Market 12 – YES" with
+/// symbol "M12YES". The token's reference is the market's own.
+fn outcome_token_metadata(
+    market_id: u32,
+    outcome: &str,
+    metadata: &MarketMetadata,
+) -> FungibleTokenMetadata {
+    let outcome_symbol = outcome
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .take(8)
+        .collect::<String>();
+
+    FungibleTokenMetadata {
+        spec: FT_METADATA_SPEC.to_string(),
+        name: format!("Market {market_id} – {outcome}"),
+        symbol: format!("M{market_id}{outcome_symbol}"),
+        icon: None,
+        reference: metadata.reference.clone(),
+        reference_hash: metadata.reference_hash.clone(),
+        decimals: 24,
+    }
+}
+
+/// A single match, as recorded in a market's trade history.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Trade {
+    timestamp: U64,
+    /// Implied probability of the long side, in basis points. Offers are
+    /// matched at even money, so this is always 5000 for now.
+    price: u16,
+    /// Collateral put up by each side.
+    size: U128,
+    taker_is_long: bool,
+}
+
+/// Running counters kept on every market. All amounts are in yoctoNEAR.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MarketStats {
+    /// Collateral ever matched on this market, counting both sides.
+    volume: U128,
+    /// Collateral currently locked in shares. On markets with outcome tokens,
+    /// this is the pool redemptions are paid from, so it stays locked until
+    /// the tokens are redeemed.
+    open_interest: U128,
+    /// Collateral resting in open long offers.
+    long_liquidity: U128,
+    /// Collateral resting in open short offers.
+    short_liquidity: U128,
+    /// Number of distinct accounts that have made or accepted an offer.
+    participants: u32,
+}
+
+impl Default for MarketStats {
+    fn default() -> Self {
+        Self {
+            volume: 0.into(),
+            open_interest: 0.into(),
+            long_liquidity: 0.into(),
+            short_liquidity: 0.into(),
+            participants: 0,
+        }
+    }
+}
+
+impl Market {
+    fn add_participant(&mut self, account_id: &AccountId) {
+        if self.participants.insert(account_id.clone()) {
+            self.stats.participants += 1;
+        }
+    }
+
+    /// Appends `trade` to the history, overwriting the oldest entry once
+    /// [`TRADE_HISTORY_LEN`] trades are stored.
+    fn record_trade(&mut self, trade: Trade) {
+        if self.trades.len() < TRADE_HISTORY_LEN {
+            self.trades.push(trade);
+        } else {
+            let slot = (self.trade_count % TRADE_HISTORY_LEN as u64) as u32;
+            self.trades.set(slot, trade);
+        }
+        self.trade_count += 1;
+    }
+
+    /// Stored trades, oldest first.
+    fn trade_history(&self) -> impl Iterator<Item = &Trade> {
+        let len = self.trades.len();
+        let start = if len < TRADE_HISTORY_LEN {
+            0
+        } else {
+            (self.trade_count % TRADE_HISTORY_LEN as u64) as u32
+        };
+        (0..len).filter_map(move |i| self.trades.get((start + i) % len))
+    }
+
+    /// Deposit each side of a match pays to be registered with its outcome token.
+    fn registration_deposit(&self) -> Balance {
+        if self.outcome_tokens {
+            OUTCOME_TOKEN_REGISTRATION
+        } else {
+            0
+        }
+    }
+
+    fn side_liquidity(&mut self, is_long: bool) -> &mut U128 {
+        if is_long {
+            &mut self.stats.long_liquidity
+        } else {
+            &mut self.stats.short_liquidity
+        }
+    }
+
+    pub fn status(&self) -> MarketStatus {
+        match (self.is_open, self.outcome) {
+            (true, _) => MarketStatus::Open,
+            (false, Some(_)) => MarketStatus::Resolved,
+            (false, None) => MarketStatus::Closed,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum MarketStatus {
+    Open,
+    Closed,
+    Resolved,
+}
+
+/// Filters for [`Contract::list_markets`]. Every field is optional; unset
+/// fields match everything except `status`, which defaults to open markets.
+#[derive(Deserialize, Default, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MarketFilter {
+    status: Option<MarketStatus>,
+    owner: Option<AccountId>,
+    token_contract: Option<AccountId>,
+    created_after: Option<U64>,
+    created_before: Option<U64>,
+}
+
+impl MarketFilter {
+    fn matches(&self, market: &Market) -> bool {
+        self.status.unwrap_or(MarketStatus::Open) == market.status()
+            && self.owner.as_ref().map_or(true, |o| o == &market.owner)
+            && self
+                .token_contract
+                .as_ref()
+                .map_or(true, |t| t == &market.token_contract)
+            && self.created_after.map_or(true, |t| market.created_at >= t.0)
+            && self.created_before.map_or(true, |t| market.created_at < t.0)
+    }
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
@@ -75,6 +472,61 @@ pub struct Offer {
     amount: U128,
 }
 
+/// Stored form of [`Market`]. New layouts get a new variant, and old
+/// variants are converted to the current one when read.
+#[derive(BorshSerialize, BorshDeserialize, Debug)]
+pub enum VersionedMarket {
+    V1(Market),
+}
+
+impl VersionedMarket {
+    pub fn as_current(&self) -> &Market {
+        match self {
+            Self::V1(m) => m,
+        }
+    }
+
+    pub fn as_current_mut(&mut self) -> &mut Market {
+        match self {
+            Self::V1(m) => m,
+        }
+    }
+}
+
+impl From<Market> for VersionedMarket {
+    fn from(m: Market) -> Self {
+        Self::V1(m)
+    }
+}
+
+/// Stored form of [`Offer`].
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub enum VersionedOffer {
+    V1(Offer),
+}
+
+impl VersionedOffer {
+    pub fn as_current(&self) -> &Offer {
+        match self {
+            Self::V1(o) => o,
+        }
+    }
+}
+
+impl From<Offer> for VersionedOffer {
+    fn from(o: Offer) -> Self {
+        Self::V1(o)
+    }
+}
+
+impl From<VersionedOffer> for Offer {
+    fn from(o: VersionedOffer) -> Self {
+        match o {
+            VersionedOffer::V1(o) => o,
+        }
+    }
+}
+
 #[derive(Serialize)]
 #[serde(crate = "near_sdk::serde")]
 pub struct ViewMarket<'a> {
@@ -83,6 +535,14 @@ pub struct ViewMarket<'a> {
     description: &'a str,
     owner: &'a AccountId,
     shares: u32,
+    token_contract: &'a AccountId,
+    created_at: U64,
+    status: MarketStatus,
+    stats: &'a MarketStats,
+    bond: U128,
+    resolution_deadline: U64,
+    metadata: &'a MarketMetadata,
+    outcome_tokens: bool,
 }
 
 impl<'a> From<&'a Market> for ViewMarket<'a> {
@@ -93,6 +553,14 @@ impl<'a> From<&'a Market> for ViewMarket<'a> {
             description: &v.description,
             owner: &v.owner,
             shares: v.shares.len(),
+            token_contract: &v.token_contract,
+            created_at: v.created_at.into(),
+            status: v.status(),
+            stats: &v.stats,
+            bond: v.bond,
+            resolution_deadline: v.resolution_deadline.into(),
+            metadata: &v.metadata,
+            outcome_tokens: v.outcome_tokens,
         }
     }
 }
@@ -104,14 +572,57 @@ struct SharePair {
     amount: U128,
 }
 
-#[derive(BorshSerialize, BorshDeserialize, PanicOnDefault, FungibleToken)]
+/// Outcome tokens owed to an account whose mint failed.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+struct Position {
+    account_id: AccountId,
+    is_long: bool,
+    amount: U128,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, PanicOnDefault, FungibleToken, Owner, Pause, Rbac)]
 #[fungible_token(name = "Options Market Token", symbol = "OMT", decimals = 24)]
+#[rbac(roles = "Role")]
 #[near_bindgen]
 pub struct Contract {
     next_offer_id: u32,
-    markets: Vector<Market>,
+    markets: Vector<VersionedMarket>,
+    open_markets: UnorderedSet<u32>,
+    closed_markets: Vector<u32>,
     credit: LookupMap<AccountId, u128>,
-    offers: UnorderedMap<u32, Offer>,
+    offers: UnorderedMap<u32, VersionedOffer>,
+    account_offers: LookupMap<AccountId, UnorderedSet<u32>>,
+    paused_operations: UnorderedSet<Operation>,
+    /// When false, only accounts with [`Role::MarketCreator`] can create markets.
+    open_market_creation: bool,
+    /// Fee taken from market payouts, in basis points.
+    fee_bps: u16,
+    treasury: AccountId,
+    /// Deposit required by `create_market`.
+    creator_bond: u128,
+    markets_by_category: UnorderedMap<String, UnorderedSet<u32>>,
+    markets_by_tag: UnorderedMap<String, UnorderedSet<u32>>,
+    /// `None` until the owner starts the maker reward program.
+    reward_config: Option<RewardConfig>,
+    epoch_rewards: LookupMap<u64, EpochRewards>,
+    maker_rewards: LookupMap<AccountId, MakerRewards>,
+    stakes: LookupMap<AccountId, StakeInfo>,
+    /// Fee discounts by OMT stake, in no particular order.
+    discount_tiers: Vec<DiscountTier>,
+    unstake_cooldown: u64,
+    /// Smallest amount `create_offer` accepts.
+    min_offer_amount: u128,
+    /// Token contracts `create_market` accepts. Empty means any.
+    allowed_token_contracts: UnorderedSet<AccountId>,
+    governance_config: GovernanceConfig,
+    proposals: Vector<Proposal>,
+    votes: LookupMap<(u32, AccountId), bool>,
+    /// Id of the latest voting power snapshot. 0 means none was taken yet.
+    snapshot_id: u64,
+    /// Voting power by account, as `(snapshot id, power)` pairs in snapshot order.
+    power_checkpoints: LookupMap<AccountId, Vector<(u64, u128)>>,
+    /// Set while state from before versioning is being converted.
+    migration: Option<migrate::Migration>,
 }
 
 impl Nep141Hook for Contract {
@@ -127,9 +638,10 @@ impl Nep141Hook for Contract {
 
     fn before_transfer(
         &mut self,
-        _transfer: &near_sdk_contract_tools::standard::nep141::Nep141Transfer,
+        transfer: &near_sdk_contract_tools::standard::nep141::Nep141Transfer,
     ) -> () {
-        Default::default()
+        self.checkpoint_voting_power(&transfer.sender_id);
+        self.checkpoint_voting_power(&transfer.receiver_id);
     }
 
     fn after_transfer(
@@ -140,6 +652,38 @@ impl Nep141Hook for Contract {
     }
 }
 
+fn paginate<T>(
+    iter: impl Iterator<Item = T>,
+    from_index: Option<u32>,
+    limit: Option<u32>,
+) -> impl Iterator<Item = T> {
+    iter.skip(from_index.unwrap_or(0) as usize).take(
+        limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .min(MAX_PAGE_LIMIT) as usize,
+    )
+}
+
+/// Number of markets filed under a category or tag.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LabelCount<'a> {
+    name: &'a str,
+    markets: u32,
+}
+
+/// `a * b / c`, rounded down, without overflowing in between. Panics if the
+/// result does not fit in a `u128`.
+fn mul_div(a: u128, b: u128, c: u128) -> u128 {
+    (u256::U256::from(a) * u256::U256::from(b) / u256::U256::from(c)).as_u128()
+}
+
+/// Implied probability of the long side, in basis points, given what each
+/// side staked.
+fn implied_price(long_stake: u128, short_stake: u128) -> u16 {
+    (long_stake * PRICE_DENOMINATOR / (long_stake + short_stake)) as u16
+}
+
 #[derive(BorshSerialize, BorshStorageKey)]
 pub enum StorageKey {
     Markets,
@@ -147,190 +691,941 @@ pub enum StorageKey {
     Credit,
     MarketShares(u32),
     Withdrawals(u32),
+    MarketOffers(u32),
+    AccountOffers(AccountId),
+    AccountOfferIndex,
+    OpenMarkets,
+    ClosedMarkets,
+    MarketParticipants(u32),
+    MarketTrades(u32),
+    PausedOperations,
+    StateVersion,
+    VersionedOffers,
+    Categories,
+    CategoryMarkets(String),
+    Tags,
+    TagMarkets(String),
+    EpochRewards,
+    MakerRewards,
+    Stakes,
+    AllowedTokenContracts,
+    Proposals,
+    Votes,
+    PowerCheckpoints,
+    AccountPowerCheckpoints(AccountId),
+    MarketUnminted(u32),
 }
 
 #[near_bindgen]
 impl Contract {
     #[init]
-    pub fn new() -> Self {
-        Self {
+    pub fn new(owner_id: Option<AccountId>) -> Self {
+        let owner_id = owner_id.unwrap_or_else(env::predecessor_account_id);
+        let mut contract = Self {
             next_offer_id: 0,
-            offers: UnorderedMap::new(StorageKey::Offers),
+            offers: UnorderedMap::new(StorageKey::VersionedOffers),
             credit: LookupMap::new(StorageKey::Credit),
             markets: Vector::new(StorageKey::Markets),
-        }
-    }
-
-    pub fn create_market(&mut self, description: String, outcomes: Vec<String>, token_contract: AccountId) -> ViewMarket {
-        let id = self.markets.len();
-        let owner = env::predecessor_account_id();
-
-        let m = Market {
-            id,
-            description,
-            owner: owner.clone(),
-            is_open: true,
-            shares: Vector::new(StorageKey::MarketShares(id)),
+            open_markets: UnorderedSet::new(StorageKey::OpenMarkets),
+            closed_markets: Vector::new(StorageKey::ClosedMarkets),
+            account_offers: LookupMap::new(StorageKey::AccountOfferIndex),
+            paused_operations: UnorderedSet::new(StorageKey::PausedOperations),
+            open_market_creation: true,
+            fee_bps: 0,
+            treasury: owner_id.clone(),
+            creator_bond: 0,
+            markets_by_category: UnorderedMap::new(StorageKey::Categories),
+            markets_by_tag: UnorderedMap::new(StorageKey::Tags),
+            reward_config: None,
+            epoch_rewards: LookupMap::new(StorageKey::EpochRewards),
+            maker_rewards: LookupMap::new(StorageKey::MakerRewards),
+            stakes: LookupMap::new(StorageKey::Stakes),
+            discount_tiers: vec![],
+            unstake_cooldown: DEFAULT_UNSTAKE_COOLDOWN,
+            min_offer_amount: 0,
+            allowed_token_contracts: UnorderedSet::new(StorageKey::AllowedTokenContracts),
+            governance_config: GovernanceConfig::default(),
+            proposals: Vector::new(StorageKey::Proposals),
+            votes: LookupMap::new(StorageKey::Votes),
+            snapshot_id: 0,
+            power_checkpoints: LookupMap::new(StorageKey::PowerCheckpoints),
+            migration: None,
         };
 
-        // Mint tokens
-        let promise = Promise::new(token_contract.clone())
-            .function_call(
-                "mint_tokens".into(),
-                format!("{{\"account_id\": \"{}\", \"amount\": \"{}\"}}", env::current_account_id(), total_supply).into_bytes(),
-                0,
-                GAS_FOR_MINT_AND_BURN,
-            );
+        Owner::init(&mut contract, &owner_id);
+        migrate::write_state_version();
 
-        self.markets.insert(&market_id, &Market {
-            // ...
-            token_contract,
-        });  
-        
-        let account_id: AccountId = format!("{}.{}", 0, env::current_account_id()).parse().unwrap();
-        Promise::new(account_id.clone()).deploy_contract(TOKEN_CONTRACT_WASM.to_vec()).then(Self::ext(env::current_account_id()).after_market_create(/* ... */));
+        contract
+    }
 
-        ContractEvent::MarketCreated {
-            market_id: id,
-            owner,
-        }
-        .emit();
+    /// Panics if the whole contract or `operation` is paused, or a state
+    /// migration is unfinished.
+    fn require_active(&self, operation: Operation) {
+        self.require_migrated();
+        Self::require_unpaused();
+        require!(
+            !self.paused_operations.contains(&operation),
+            "This operation is currently paused."
+        );
+    }
 
-        self.markets.get(id).unwrap().into()
+    /// Pauses every pausable entrypoint. Owner only.
+    pub fn pause_contract(&mut self) {
+        Self::require_owner();
+        Pause::pause(self);
     }
 
-    fn credit_account(&mut self, account_id: AccountId, amount: u128) {
-        *self.credit.entry(account_id).or_insert(0) += amount;
+    /// Lifts a contract-wide pause. Operations paused individually stay
+    /// paused. Owner only.
+    pub fn unpause_contract(&mut self) {
+        Self::require_owner();
+        Pause::unpause(self);
     }
 
-    pub fn withdraw(&mut self) -> Promise {
-        let predecessor = env::predecessor_account_id();
-        let amount = self
-            .credit
-            .remove(&predecessor)
-            .unwrap_or_else(|| env::panic_str("You have no rewards to withdraw."));
+    /// Pauses a single entrypoint. Owner only.
+    pub fn pause_operation(&mut self, operation: Operation) {
+        Self::require_owner();
+        require!(
+            self.paused_operations.insert(operation),
+            "Operation is already paused."
+        );
 
-        Promise::new(predecessor).transfer(amount)
+        ContractEvent::OperationPaused { operation }.emit();
     }
 
-    pub fn close_market(&mut self, market_id: u32, is_long: bool) {
-        let market = self
-            .markets
-            .get_mut(market_id)
-            .unwrap_or_else(|| env::panic_str("Market does not exist!"));
-        require!(market.is_open, "Market is already closed.");
-        let predecessor = env::predecessor_account_id();
+    /// Unpauses a single entrypoint. Owner only.
+    pub fn unpause_operation(&mut self, operation: Operation) {
+        Self::require_owner();
         require!(
-            market.owner == predecessor,
-            "You are not allowed to close a market you did not create."
+            self.paused_operations.remove(&operation),
+            "Operation is not paused."
         );
-        market.is_open = false;
 
-        let credits = market
-            .shares
-            .iter()
-            .map(|s| {
-                (
-                    if is_long {
-                        s.long.clone()
-                    } else {
-                        s.short.clone()
-                    },
-                    s.amount,
-                )
-            })
-            .collect::<Vec<_>>();
+        ContractEvent::OperationUnpaused { operation }.emit();
+    }
 
-        ContractEvent::MarketClosed { market_id }.emit();
+    pub fn get_paused_operations(&self) -> Vec<Operation> {
+        self.paused_operations.iter().copied().collect()
+    }
 
-        let promise = Promise::new(market.token_contract.clone())
-            .function_call(
-                "burn_tokens".into(),
-                format!("{{\"account_id\": \"{}\", \"amount\": \"{}\"}}", env::current_account_id(), total_supply).into_bytes(),
-                0,
-                GAS_FOR_MINT_AND_BURN,
-            );
+    /// Owner only.
+    pub fn grant_role(&mut self, account_id: AccountId, role: Role) {
+        Self::require_owner();
+        self.add_role(account_id.clone(), &role);
 
-        drop(market);
+        ContractEvent::RoleGranted { account_id, role }.emit();
+    }
 
-        for (creditor, amount) in credits {
-            self.credit_account(creditor, amount.0 * 2);
-        }
+    /// Owner only.
+    pub fn revoke_role(&mut self, account_id: AccountId, role: Role) {
+        Self::require_owner();
+        self.remove_role(&account_id, &role);
+
+        ContractEvent::RoleRevoked { account_id, role }.emit();
     }
 
-    pub fn get_market(&self, market_id: u32) -> Option<ViewMarket> {
-        self.markets.get(market_id).map(|m| m.into())
+    pub fn account_has_role(&self, account_id: AccountId, role: Role) -> bool {
+        Self::has_role(&account_id, &role)
     }
 
-    pub fn list_markets(&self) -> Vec<ViewMarket> {
-        self.markets.iter().map(|m| m.into()).collect()
+    /// Allows anyone (`true`) or only [`Role::MarketCreator`] holders
+    /// (`false`) to create markets. Owner only.
+    pub fn set_open_market_creation(&mut self, open: bool) {
+        Self::require_owner();
+        self.open_market_creation = open;
     }
 
-    pub fn get_offers(&self, market_id: u32) -> Vec<Offer> {
-        self.offers
-            .iter()
-            .filter_map(|(_, b)| {
-                if b.market_id == market_id {
-                    Some(b.clone())
-                } else {
-                    None
-                }
-            })
-            .collect()
+    pub fn is_open_market_creation(&self) -> bool {
+        self.open_market_creation
     }
 
-    #[payable]
-    pub fn create_offer(&mut self, market_id: u32, is_long: bool) -> Offer {
-        let amount = env::attached_deposit();
+    /// Sets the payout fee and the account it is credited to.
+    /// [`Role::FeeManager`] only.
+    pub fn set_fee(&mut self, fee_bps: u16, treasury: Option<AccountId>) {
+        Self::require_role(&Role::FeeManager);
         require!(
-            amount > 0,
-            "You must attach a nonzero amount to make an offer."
+            (fee_bps as u128) < FEE_DENOMINATOR,
+            "Fee must be below 100%."
         );
+        self.fee_bps = fee_bps;
+        if let Some(treasury) = treasury {
+            self.treasury = treasury;
+        }
 
-        let id = self.next_offer_id;
-        self.next_offer_id += 1;
-        let account_id = env::predecessor_account_id();
-        let o = Offer {
-            id,
-            is_long,
-            account_id: account_id.clone(),
-            amount: amount.into(),
-            market_id,
-        };
-
-        self.offers.insert(id, o.clone());
-
-        ContractEvent::OfferCreated {
-            offer_id: id,
-            market_id,
-            is_long,
-            account_id,
-            amount: amount.into(),
+        ContractEvent::FeeUpdated {
+            fee_bps,
+            treasury: self.treasury.clone(),
         }
         .emit();
+    }
 
-        o
+    pub fn get_fee(&self) -> (u16, &AccountId) {
+        (self.fee_bps, &self.treasury)
     }
 
-    #[payable]
-    pub fn accept_offer(&mut self, offer_id: u32) {
-        let amount = env::attached_deposit();
-        require!(
-            amount > 0,
-            "You must attach a nonzero amount to accept an offer."
-        );
-        let amount: U128 = amount.into();
+    /// Sets the deposit `create_market` requires. Owner only.
+    pub fn set_creator_bond(&mut self, amount: U128) {
+        Self::require_owner();
+        self.creator_bond = amount.0;
+    }
 
-        let o = self.offers.remove(&offer_id).unwrap_or_else(|| {
-            env::panic_str("Offer does not exist. Maybe someone already accepted it?")
-        });
+    pub fn get_creator_bond(&self) -> U128 {
+        self.creator_bond.into()
+    }
 
+    /// Proposes `new_owner` as the owner of both outcome tokens of a market,
+    /// e.g. a successor market contract, which then has to call
+    /// `accept_ownership` on each token. `None` withdraws the proposal.
+    /// Owner only.
+    ///
+    /// Redemptions are paid from the market's pool, which stays with this
+    /// contract, so a market can only be handed off once it has closed and
+    /// all of its collateral has been redeemed.
+    pub fn propose_outcome_token_owner(&mut self, market_id: u32, new_owner: Option<AccountId>) {
+        Self::require_owner();
+        let market = self
+            .markets
+            .get(market_id)
+            .map(VersionedMarket::as_current)
+            .unwrap_or_else(|| env::panic_str("Market does not exist!"));
         require!(
-            o.amount == amount,
-            "You must attach exactly the same amount as the offer you are accepting."
+            new_owner.is_none() || (!market.is_open && market.stats.open_interest.0 == 0),
+            "Market still has collateral to redeem."
         );
-        let predecessor = env::predecessor_account_id();
-        require!(
+
+        let args = json!({ "account_id": new_owner }).to_string().into_bytes();
+        for i in 0..2 {
+            Promise::new(outcome_token_account(market_id, i)).function_call(
+                "propose_owner".into(),
+                args.clone(),
+                0,
+                GAS_FOR_TOKEN_OWNERSHIP,
+            );
+        }
+    }
+
+    /// Accepts ownership of an outcome token this contract was proposed as
+    /// the owner of. Owner only.
+    pub fn accept_outcome_token_ownership(&mut self, token_id: AccountId) -> Promise {
+        Self::require_owner();
+        Promise::new(token_id).function_call(
+            "accept_ownership".into(),
+            vec![],
+            0,
+            GAS_FOR_TOKEN_OWNERSHIP,
+        )
+    }
+
+    /// Deposit `create_market` requires: the creator bond plus the balance
+    /// each of the two outcome token accounts is created with.
+    pub fn get_market_creation_deposit(&self) -> U128 {
+        (self.creator_bond + 2 * outcome_token_deposit()).into()
+    }
+
+    #[payable]
+    pub fn create_market(
+        &mut self,
+        description: String,
+        outcomes: Vec<String>,
+        token_contract: AccountId,
+        resolution_deadline: U64,
+        metadata: MarketMetadata,
+    ) -> ViewMarket {
+        self.require_active(Operation::CreateMarket);
+        if !self.open_market_creation {
+            Self::require_role(&Role::MarketCreator);
+        }
+        let creation_deposit = self.get_market_creation_deposit().0;
+        require!(
+            env::attached_deposit() == creation_deposit,
+            format!(
+                "You must attach exactly {} yoctoNEAR: the creator bond plus the outcome token deposits.",
+                creation_deposit
+            )
+        );
+        require!(
+            resolution_deadline.0 > env::block_timestamp(),
+            "Resolution deadline must be in the future."
+        );
+        require!(
+            self.allowed_token_contracts.is_empty()
+                || self.allowed_token_contracts.contains(&token_contract),
+            "This token contract is not allowed."
+        );
+        require!(
+            description.len() <= MAX_DESCRIPTION_LEN,
+            "Description is too long."
+        );
+        metadata.assert_valid();
+        require!(
+            metadata.end_date.0 <= resolution_deadline.0,
+            "End date must not be after the resolution deadline."
+        );
+        require!(
+            outcomes.len() == 2,
+            "A market needs exactly two outcomes, long first."
+        );
+        for outcome in &outcomes {
+            require!(
+                !outcome.is_empty() && outcome.len() <= MAX_OUTCOME_LEN,
+                "Outcomes must be between 1 and 32 bytes."
+            );
+        }
+
+        let id = self.markets.len();
+        let owner = env::predecessor_account_id();
+        let token_metadata = outcomes
+            .iter()
+            .map(|outcome| outcome_token_metadata(id, outcome, &metadata))
+            .collect::<Vec<_>>();
+
+        let m = Market {
+            id,
+            description,
+            owner: owner.clone(),
+            is_open: true,
+            shares: Vector::new(StorageKey::MarketShares(id)),
+            offers: UnorderedSet::new(StorageKey::MarketOffers(id)),
+            token_contract: token_contract.clone(),
+            created_at: env::block_timestamp(),
+            outcome: None,
+            stats: MarketStats::default(),
+            participants: LookupSet::new(StorageKey::MarketParticipants(id)),
+            trades: Vector::new(StorageKey::MarketTrades(id)),
+            trade_count: 0,
+            bond: self.creator_bond.into(),
+            resolution_deadline: resolution_deadline.0,
+            metadata,
+            outcome_tokens: true,
+            unminted: Vector::new(StorageKey::MarketUnminted(id)),
+        };
+
+        self.index_market(id, &m.metadata);
+        self.markets.push(m.into());
+        self.open_markets.insert(id);
+
+        // Deploy one token per outcome, owned by this contract
+        for (i, token_metadata) in token_metadata.into_iter().enumerate() {
+            let account_id = outcome_token_account(id, i);
+            let args = json!({
+                "owner_contract_id": env::current_account_id(),
+                "total_supply": U128(0),
+                "metadata": token_metadata,
+            });
+            Promise::new(account_id)
+                .create_account()
+                .transfer(outcome_token_deposit())
+                .deploy_contract(TOKEN_CONTRACT_WASM.to_vec())
+                .function_call("new".into(), args.to_string().into_bytes(), 0, GAS_FOR_TOKEN_INIT)
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(GAS_FOR_ON_OUTCOME_TOKEN_CREATED)
+                        .on_outcome_token_created(owner.clone()),
+                );
+        }
+
+        ContractEvent::MarketCreated {
+            market_id: id,
+            owner,
+        }
+        .emit();
+
+        self.markets.get(id).unwrap().as_current().into()
+    }
+
+    /// Credits the deposit for an outcome token back to the market's
+    /// creator if the token could not be created. The account creation
+    /// batch is all-or-nothing, so nothing is left behind to clean up.
+    #[private]
+    pub fn on_outcome_token_created(
+        &mut self,
+        creator: AccountId,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        if result.is_ok() {
+            return true;
+        }
+
+        self.credit_account(creator, outcome_token_deposit());
+        false
+    }
+
+    /// Files a market under its category and each of its tags.
+    fn index_market(&mut self, market_id: u32, metadata: &MarketMetadata) {
+        self.markets_by_category
+            .entry(metadata.category.clone())
+            .or_insert_with(|| {
+                UnorderedSet::new(StorageKey::CategoryMarkets(metadata.category.clone()))
+            })
+            .insert(market_id);
+
+        for tag in &metadata.tags {
+            self.markets_by_tag
+                .entry(tag.clone())
+                .or_insert_with(|| UnorderedSet::new(StorageKey::TagMarkets(tag.clone())))
+                .insert(market_id);
+        }
+    }
+
+    fn credit_account(&mut self, account_id: AccountId, amount: u128) {
+        if amount == 0 {
+            return;
+        }
+
+        *self.credit.entry(account_id.clone()).or_insert(0) += amount;
+
+        ContractEvent::Credits {
+            account_id,
+            amount: amount.into(),
+        }
+        .emit();
+    }
+
+    /// Withdrawable balance of `account_id`.
+    pub fn get_credit(&self, account_id: AccountId) -> U128 {
+        self.credit.get(&account_id).copied().unwrap_or(0).into()
+    }
+
+    /// Withdrawable balances of `accounts`, in the same order.
+    pub fn get_credits(&self, accounts: Vec<AccountId>) -> Vec<U128> {
+        require!(
+            accounts.len() <= MAX_PAGE_LIMIT as usize,
+            "Too many accounts requested."
+        );
+
+        accounts
+            .iter()
+            .map(|a| self.credit.get(a).copied().unwrap_or(0).into())
+            .collect()
+    }
+
+    pub fn withdraw(&mut self) -> Promise {
+        self.require_active(Operation::Withdraw);
+
+        let predecessor = env::predecessor_account_id();
+        let amount = self
+            .credit
+            .remove(&predecessor)
+            .unwrap_or_else(|| env::panic_str("You have no rewards to withdraw."));
+
+        Promise::new(predecessor).transfer(amount)
+    }
+
+    pub fn close_market(&mut self, market_id: u32, is_long: bool) {
+        self.require_migrated();
+        let market = self
+            .markets
+            .get_mut(market_id)
+            .map(VersionedMarket::as_current_mut)
+            .unwrap_or_else(|| env::panic_str("Market does not exist!"));
+        require!(market.is_open, "Market is already closed.");
+        let predecessor = env::predecessor_account_id();
+        require!(
+            market.owner == predecessor || Self::has_role(&predecessor, &Role::Resolver),
+            "You are not allowed to close a market you did not create."
+        );
+        market.is_open = false;
+        market.outcome = Some(is_long);
+        let bond = std::mem::take(&mut market.bond.0);
+        let resolved_in_time = env::block_timestamp() <= market.resolution_deadline;
+        let creator = market.owner.clone();
+        let outcome_tokens = market.outcome_tokens;
+
+        ContractEvent::MarketClosed { market_id }.emit();
+
+        if outcome_tokens {
+            // Winners redeem their tokens from the pool; only tokens that
+            // were never minted are paid out here.
+            let positions = market.unminted.iter().cloned().collect::<Vec<_>>();
+
+            // The losing side's token is worthless from now on
+            let losing_token = outcome_token_account(market_id, if is_long { 1 } else { 0 });
+            Promise::new(losing_token).function_call(
+                "freeze".into(),
+                vec![],
+                0,
+                GAS_FOR_TOKEN_FREEZE,
+            );
+
+            self.open_markets.remove(&market_id);
+            self.closed_markets.push(market_id);
+            for p in positions {
+                self.pay_out_tokens(market_id, p.account_id, p.is_long, p.amount.0);
+            }
+        } else {
+            market.stats.open_interest = 0.into();
+            let credits = market
+                .shares
+                .iter()
+                .map(|s| {
+                    (
+                        if is_long {
+                            s.long.clone()
+                        } else {
+                            s.short.clone()
+                        },
+                        s.amount,
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            self.open_markets.remove(&market_id);
+            self.closed_markets.push(market_id);
+
+            let mut fees = 0;
+            for (creditor, amount) in credits {
+                let payout = amount.0 * 2;
+                let fee = self.fee_for(&creditor, payout);
+                fees += fee;
+                self.credit_account(creditor, payout - fee);
+            }
+            self.credit_account(self.treasury.clone(), fees);
+        }
+        self.cancel_offers(market_id);
+
+        if resolved_in_time {
+            self.credit_account(creator, bond);
+        } else {
+            self.slash_bond(market_id, bond);
+        }
+    }
+
+    fn slash_bond(&mut self, market_id: u32, amount: u128) {
+        if amount == 0 {
+            return;
+        }
+
+        self.credit_account(self.treasury.clone(), amount);
+
+        ContractEvent::BondSlashed {
+            market_id,
+            amount: amount.into(),
+        }
+        .emit();
+    }
+
+    /// Sends the creator bond of a market that is still open after its
+    /// resolution deadline to the treasury. Callable by anyone.
+    pub fn slash_expired_bond(&mut self, market_id: u32) {
+        let market = self
+            .markets
+            .get_mut(market_id)
+            .map(VersionedMarket::as_current_mut)
+            .unwrap_or_else(|| env::panic_str("Market does not exist!"));
+        require!(market.is_open, "Market is already closed.");
+        require!(
+            env::block_timestamp() > market.resolution_deadline,
+            "Market has not passed its resolution deadline."
+        );
+        let bond = std::mem::take(&mut market.bond.0);
+        require!(bond > 0, "Market has no bond left to slash.");
+
+        self.slash_bond(market_id, bond);
+    }
+
+    /// Closes a market without an outcome and refunds both sides of every
+    /// share, fee-free. [`Role::Moderator`] only.
+    pub fn invalidate_market(&mut self, market_id: u32) {
+        Self::require_role(&Role::Moderator);
+        self.require_migrated();
+        let market = self
+            .markets
+            .get_mut(market_id)
+            .map(VersionedMarket::as_current_mut)
+            .unwrap_or_else(|| env::panic_str("Market does not exist!"));
+        require!(market.is_open, "Market is already closed.");
+        market.is_open = false;
+        let bond = std::mem::take(&mut market.bond.0);
+
+        ContractEvent::MarketInvalidated { market_id }.emit();
+
+        if market.outcome_tokens {
+            // Token holders redeem either side for half its face value.
+            let positions = market.unminted.iter().cloned().collect::<Vec<_>>();
+
+            self.open_markets.remove(&market_id);
+            self.closed_markets.push(market_id);
+            for p in positions {
+                self.pay_out_tokens(market_id, p.account_id, p.is_long, p.amount.0);
+            }
+        } else {
+            market.stats.open_interest = 0.into();
+            let refunds = market
+                .shares
+                .iter()
+                .flat_map(|s| [(s.long.clone(), s.amount), (s.short.clone(), s.amount)])
+                .collect::<Vec<_>>();
+
+            self.open_markets.remove(&market_id);
+            self.closed_markets.push(market_id);
+            for (creditor, amount) in refunds {
+                self.credit_account(creditor, amount.0);
+            }
+        }
+        self.cancel_offers(market_id);
+        self.slash_bond(market_id, bond);
+    }
+
+    /// Cancels the offers still resting on a market that has just closed,
+    /// crediting each maker their collateral and registration deposit back.
+    fn cancel_offers(&mut self, market_id: u32) {
+        let market = self.markets.get_mut(market_id).unwrap().as_current_mut();
+        let ids = market.offers.drain().collect::<Vec<_>>();
+        market.stats.long_liquidity = 0.into();
+        market.stats.short_liquidity = 0.into();
+        let registration = market.registration_deposit();
+
+        for id in ids {
+            let o = match self.offers.remove(&id) {
+                Some(o) => Offer::from(o),
+                None => continue,
+            };
+            if let Some(index) = self.account_offers.get_mut(&o.account_id) {
+                index.remove(&id);
+            }
+            self.credit_account(o.account_id, o.amount.0 + registration);
+        }
+    }
+
+    /// Pays `amount` outcome tokens of a closed market out of its pool: the
+    /// winning side at face value minus fees, either side of an invalidated
+    /// market at half its face value, fee-free. Returns the collateral paid.
+    fn pay_out_tokens(
+        &mut self,
+        market_id: u32,
+        account_id: AccountId,
+        is_long: bool,
+        amount: u128,
+    ) -> u128 {
+        let market = self.markets.get_mut(market_id).unwrap().as_current_mut();
+        let (payout, resolved) = match market.outcome {
+            Some(outcome) if outcome == is_long => (amount, true),
+            Some(_) => (0, true),
+            None => (amount / 2, false),
+        };
+        require!(
+            market.stats.open_interest.0 >= payout,
+            "Not enough collateral left in the market."
+        );
+        market.stats.open_interest.0 -= payout;
+
+        let fee = if resolved {
+            self.fee_for(&account_id, payout)
+        } else {
+            0
+        };
+        self.credit_account(account_id, payout - fee);
+        self.credit_account(self.treasury.clone(), fee);
+        payout
+    }
+
+    /// Mints `amount` tokens of one side of a market to `account_id`,
+    /// attaching the deposit that registers it with the token.
+    fn mint_outcome_tokens(
+        &self,
+        market_id: u32,
+        account_id: AccountId,
+        is_long: bool,
+        amount: u128,
+    ) {
+        let args = json!({
+            "account_id": account_id,
+            "amount": U128(amount),
+            "memo": "match",
+        });
+        let token_id = outcome_token_account(market_id, if is_long { 0 } else { 1 });
+        Promise::new(token_id)
+            .function_call(
+                "mint_tokens".into(),
+                args.to_string().into_bytes(),
+                OUTCOME_TOKEN_REGISTRATION,
+                GAS_FOR_TOKEN_MINT,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_ON_OUTCOME_TOKENS_MINTED)
+                    .on_outcome_tokens_minted(market_id, account_id, is_long, amount.into()),
+            );
+    }
+
+    /// Credits whatever registration deposit the token refunded back to
+    /// `account_id`. If minting failed, the tokens are owed as a position
+    /// instead, paid out when the market closes. Matches can only happen
+    /// while a market is open, so a failure reported after it closed is paid
+    /// out right away.
+    #[private]
+    pub fn on_outcome_tokens_minted(
+        &mut self,
+        market_id: u32,
+        account_id: AccountId,
+        is_long: bool,
+        amount: U128,
+        #[callback_result] result: Result<U128, PromiseError>,
+    ) -> bool {
+        if let Ok(refund) = result {
+            self.credit_account(account_id, refund.0);
+            return true;
+        }
+
+        // A failed call refunds its whole deposit to this contract.
+        self.credit_account(account_id.clone(), OUTCOME_TOKEN_REGISTRATION);
+        let market = self.markets.get_mut(market_id).unwrap().as_current_mut();
+        if market.is_open {
+            market.unminted.push(Position {
+                account_id,
+                is_long,
+                amount,
+            });
+        } else {
+            self.pay_out_tokens(market_id, account_id, is_long, amount.0);
+        }
+        false
+    }
+
+    /// Pays out collateral from the market's pool to `account_id` for
+    /// `amount` outcome tokens it has burned: at face value minus fees for the
+    /// winning outcome of a resolved market, or at half its face value for
+    /// either outcome of an invalidated one. Only callable by the outcome
+    /// token, through its `ft_redeem`.
+    pub fn redeem_outcome_tokens(&mut self, account_id: AccountId, amount: U128) {
+        let (market_id, index) = parse_outcome_token_account(&env::predecessor_account_id())
+            .unwrap_or_else(|| env::panic_str("Only outcome tokens can redeem."));
+        let market = self
+            .markets
+            .get(market_id)
+            .map(VersionedMarket::as_current)
+            .unwrap_or_else(|| env::panic_str("Market does not exist!"));
+        require!(!market.is_open, "Market has not been resolved.");
+        let is_long = index == 0;
+        require!(
+            market.outcome.map_or(true, |outcome| outcome == is_long),
+            "Only the winning outcome can be redeemed."
+        );
+
+        self.pay_out_tokens(market_id, account_id.clone(), is_long, amount.0);
+
+        ContractEvent::TokensRedeemed {
+            market_id,
+            account_id,
+            amount,
+        }
+        .emit();
+    }
+
+    pub fn get_market(&self, market_id: u32) -> Option<ViewMarket> {
+        self.markets.get(market_id).map(|m| m.as_current().into())
+    }
+
+    /// Markets matching `filter`, paginated over the matching set. Open
+    /// markets are read from their own index, so the default listing does not
+    /// walk closed or resolved markets.
+    pub fn list_markets(
+        &self,
+        filter: Option<MarketFilter>,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<ViewMarket> {
+        let filter = filter.unwrap_or_default();
+        let ids: Box<dyn Iterator<Item = &u32>> = match filter.status {
+            None | Some(MarketStatus::Open) => Box::new(self.open_markets.iter()),
+            Some(MarketStatus::Closed | MarketStatus::Resolved) => {
+                Box::new(self.closed_markets.iter())
+            }
+        };
+
+        paginate(
+            ids.filter_map(|id| self.markets.get(*id).map(VersionedMarket::as_current))
+                .filter(|m| filter.matches(m)),
+            from_index,
+            limit,
+        )
+        .map(|m| m.into())
+        .collect()
+    }
+
+    /// Markets filed under `category`, in index order.
+    pub fn list_markets_by_category(
+        &self,
+        category: String,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<ViewMarket> {
+        self.list_indexed_markets(self.markets_by_category.get(&category), from_index, limit)
+    }
+
+    /// Markets tagged with `tag`, in index order.
+    pub fn list_markets_by_tag(
+        &self,
+        tag: String,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<ViewMarket> {
+        self.list_indexed_markets(self.markets_by_tag.get(&tag), from_index, limit)
+    }
+
+    fn list_indexed_markets(
+        &self,
+        index: Option<&UnorderedSet<u32>>,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<ViewMarket> {
+        let index = match index {
+            Some(i) => i,
+            None => return vec![],
+        };
+
+        paginate(index.iter(), from_index, limit)
+            .filter_map(|id| self.markets.get(*id))
+            .map(|m| m.as_current().into())
+            .collect()
+    }
+
+    /// Every category in use, with the number of markets filed under it.
+    pub fn list_categories(&self, from_index: Option<u32>, limit: Option<u32>) -> Vec<LabelCount> {
+        paginate(self.markets_by_category.iter(), from_index, limit)
+            .map(|(name, markets)| LabelCount {
+                name,
+                markets: markets.len(),
+            })
+            .collect()
+    }
+
+    /// Every tag in use, with the number of markets carrying it.
+    pub fn list_tags(&self, from_index: Option<u32>, limit: Option<u32>) -> Vec<LabelCount> {
+        paginate(self.markets_by_tag.iter(), from_index, limit)
+            .map(|(name, markets)| LabelCount {
+                name,
+                markets: markets.len(),
+            })
+            .collect()
+    }
+
+    /// Recent trades on a market, oldest first. Only the last
+    /// [`TRADE_HISTORY_LEN`] trades are kept. See [`Trade::price`] for why
+    /// prices do not move yet.
+    pub fn get_trades(&self, market_id: u32, from: Option<u32>, limit: Option<u32>) -> Vec<Trade> {
+        match self.markets.get(market_id).map(VersionedMarket::as_current) {
+            Some(m) => paginate(m.trade_history(), from, limit).cloned().collect(),
+            None => vec![],
+        }
+    }
+
+    /// Price of the most recent trade on a market, in basis points. Always
+    /// 5000 while offers are matched at even money.
+    pub fn get_last_price(&self, market_id: u32) -> Option<u16> {
+        let market = self.markets.get(market_id)?.as_current();
+        let last = market.trade_count.checked_sub(1)?;
+        market
+            .trades
+            .get((last % TRADE_HISTORY_LEN as u64) as u32)
+            .map(|t| t.price)
+    }
+
+    /// Open offers on a market, in index order. `from_index` skips that
+    /// many offers; `limit` defaults to [`DEFAULT_PAGE_LIMIT`] and is capped
+    /// at [`MAX_PAGE_LIMIT`].
+    pub fn get_offers(
+        &self,
+        market_id: u32,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<Offer> {
+        let market = match self.markets.get(market_id).map(VersionedMarket::as_current) {
+            Some(m) => m,
+            None => return vec![],
+        };
+
+        paginate(market.offers.iter(), from_index, limit)
+            .filter_map(|id| self.offers.get(id).map(|o| o.as_current().clone()))
+            .collect()
+    }
+
+    /// Open offers made by `account_id`, in index order.
+    pub fn get_offers_by_account(
+        &self,
+        account_id: AccountId,
+        from_index: Option<u32>,
+        limit: Option<u32>,
+    ) -> Vec<Offer> {
+        let index = match self.account_offers.get(&account_id) {
+            Some(i) => i,
+            None => return vec![],
+        };
+
+        paginate(index.iter(), from_index, limit)
+            .filter_map(|id| self.offers.get(id).map(|o| o.as_current().clone()))
+            .collect()
+    }
+
+    #[payable]
+    pub fn create_offer(&mut self, market_id: u32, is_long: bool) -> Offer {
+        self.require_active(Operation::CreateOffer);
+
+        let market = self
+            .markets
+            .get_mut(market_id)
+            .map(VersionedMarket::as_current_mut)
+            .unwrap_or_else(|| env::panic_str("Market does not exist!"));
+        require!(market.is_open, "Market is closed.");
+
+        // The maker's registration deposit is kept for the mint on a match.
+        let amount = env::attached_deposit().saturating_sub(market.registration_deposit());
+        require!(
+            amount > 0,
+            "You must attach a nonzero amount, plus the registration deposit on markets with outcome tokens, to make an offer."
+        );
+        require!(
+            amount >= self.min_offer_amount,
+            "Offer is below the minimum offer amount."
+        );
+
+        let id = self.next_offer_id;
+        self.next_offer_id += 1;
+        market.offers.insert(id);
+
+        let account_id = env::predecessor_account_id();
+        market.side_liquidity(is_long).0 += amount;
+        market.add_participant(&account_id);
+        self.account_offers
+            .entry(account_id.clone())
+            .or_insert_with(|| UnorderedSet::new(StorageKey::AccountOffers(account_id.clone())))
+            .insert(id);
+
+        let o = Offer {
+            id,
+            is_long,
+            account_id: account_id.clone(),
+            amount: amount.into(),
+            market_id,
+        };
+
+        self.offers.insert(id, o.clone().into());
+
+        ContractEvent::OfferCreated {
+            offer_id: id,
+            market_id,
+            is_long,
+            account_id,
+            amount: amount.into(),
+        }
+        .emit();
+
+        o
+    }
+
+    #[payable]
+    pub fn accept_offer(&mut self, offer_id: u32) {
+        self.require_active(Operation::AcceptOffer);
+
+        let o: Offer = self
+            .offers
+            .remove(&offer_id)
+            .unwrap_or_else(|| {
+                env::panic_str("Offer does not exist. Maybe someone already accepted it?")
+            })
+            .into();
+        let predecessor = env::predecessor_account_id();
+        require!(
             predecessor != o.account_id,
             "You cannot accept your own offer."
         );
@@ -338,7 +1633,29 @@ impl Contract {
         let market = self
             .markets
             .get_mut(o.market_id)
+            .map(VersionedMarket::as_current_mut)
             .unwrap_or_else(|| env::panic_str("Market no longer exists!"));
+        require!(market.is_open, "Market is closed.");
+        require!(
+            env::attached_deposit() == o.amount.0 + market.registration_deposit(),
+            "You must attach exactly the same amount as the offer you are accepting, plus the registration deposit on markets with outcome tokens."
+        );
+        market.offers.remove(&offer_id);
+        market.side_liquidity(o.is_long).0 -= o.amount.0;
+        market.stats.volume.0 += o.amount.0 * 2;
+        market.stats.open_interest.0 += o.amount.0 * 2;
+        market.add_participant(&predecessor);
+        market.record_trade(Trade {
+            timestamp: env::block_timestamp().into(),
+            // Both sides of a match stake the same amount, so the price is always 50%.
+            price: implied_price(o.amount.0, o.amount.0),
+            size: o.amount,
+            taker_is_long: !o.is_long,
+        });
+
+        if let Some(index) = self.account_offers.get_mut(&o.account_id) {
+            index.remove(&offer_id);
+        }
 
         ContractEvent::OfferAccepted {
             offer_id,
@@ -347,18 +1664,381 @@ impl Contract {
         }
         .emit();
 
+        let maker = o.account_id.clone();
         let (long, short) = if o.is_long {
             (o.account_id, predecessor)
         } else {
             (predecessor, o.account_id)
         };
 
-        market.shares.push(SharePair {
-            long,
-            short,
-            amount: o.amount,
-        });
+        if market.outcome_tokens {
+            // Each side gets tokens for the whole pot, which is what the
+            // winning side redeems them for.
+            let amount = o.amount.0 * 2;
+            self.mint_outcome_tokens(o.market_id, long, true, amount);
+            self.mint_outcome_tokens(o.market_id, short, false, amount);
+        } else {
+            market.shares.push(SharePair {
+                long,
+                short,
+                amount: o.amount,
+            });
+        }
+
+        self.record_maker_volume(&maker, o.amount.0);
+    }
+
+
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    const BOND: Balance = 1_000;
+    const DEADLINE: u64 = 100;
+
+    /// A call from `predecessor` to the contract, which is deployed to
+    /// `accounts(0)`.
+    fn context(predecessor: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .predecessor_account_id(predecessor);
+        builder
+    }
+
+    /// A contract owned by `accounts(0)`, which is also the treasury.
+    fn setup() -> Contract {
+        testing_env!(context(accounts(0)).build());
+        let mut contract = Contract::new(None);
+        contract.set_creator_bond(BOND.into());
+        contract
+    }
+
+    /// Creates a market as `accounts(1)`, to be resolved by [`DEADLINE`].
+    fn create_market(contract: &mut Contract) -> u32 {
+        testing_env!(context(accounts(1))
+            .attached_deposit(contract.get_market_creation_deposit().0)
+            .build());
+        contract
+            .create_market(
+                "Will it rain?".to_string(),
+                vec!["Yes".to_string(), "No".to_string()],
+                accounts(5),
+                DEADLINE.into(),
+                MarketMetadata {
+                    category: "Weather".to_string(),
+                    tags: vec![],
+                    rules: String::new(),
+                    resolution_source: None,
+                    reference: None,
+                    reference_hash: None,
+                    end_date: DEADLINE.into(),
+                },
+            )
+            .id
+    }
+
+    fn create_offer(
+        contract: &mut Contract,
+        market_id: u32,
+        maker: AccountId,
+        is_long: bool,
+        amount: Balance,
+    ) -> u32 {
+        testing_env!(context(maker)
+            .attached_deposit(amount + OUTCOME_TOKEN_REGISTRATION)
+            .build());
+        contract.create_offer(market_id, is_long).id
+    }
+
+    fn accept_offer(contract: &mut Contract, offer_id: u32, taker: AccountId, amount: Balance) {
+        testing_env!(context(taker)
+            .attached_deposit(amount + OUTCOME_TOKEN_REGISTRATION)
+            .build());
+        contract.accept_offer(offer_id);
+    }
+
+    /// Redeems `amount` tokens of one side for `account_id`, as the token does.
+    fn redeem(
+        contract: &mut Contract,
+        market_id: u32,
+        is_long: bool,
+        account_id: AccountId,
+        amount: Balance,
+    ) {
+        let token_id = outcome_token_account(market_id, if is_long { 0 } else { 1 });
+        testing_env!(context(token_id).build());
+        contract.redeem_outcome_tokens(account_id, amount.into());
+    }
+
+    /// A market where `accounts(2)` went long against `accounts(3)` with 100
+    /// each, and resolved payouts pay a 1% fee.
+    fn matched_market(contract: &mut Contract) -> u32 {
+        contract.grant_role(accounts(0), Role::FeeManager);
+        contract.set_fee(100, None);
+        let market_id = create_market(contract);
+        let offer_id = create_offer(contract, market_id, accounts(2), true, 100);
+        accept_offer(contract, offer_id, accounts(3), 100);
+        market_id
+    }
+
+    fn stored_market(contract: &Contract, market_id: u32) -> &Market {
+        contract.markets[market_id].as_current()
+    }
+
+    fn open_interest(contract: &Contract, market_id: u32) -> Balance {
+        stored_market(contract, market_id).stats.open_interest.0
+    }
+
+    #[test]
+    fn bond_returned_on_timely_close() {
+        let mut contract = setup();
+        let market_id = create_market(&mut contract);
+        assert_eq!(contract.get_market(market_id).unwrap().bond.0, BOND);
+
+        testing_env!(context(accounts(1)).block_timestamp(DEADLINE).build());
+        contract.close_market(market_id, true);
+
+        assert_eq!(contract.get_credit(accounts(1)).0, BOND);
+        assert_eq!(contract.get_credit(accounts(0)).0, 0);
+        assert_eq!(contract.get_market(market_id).unwrap().bond.0, 0);
+    }
+
+    #[test]
+    fn bond_slashed_when_resolver_closes_late() {
+        let mut contract = setup();
+        contract.grant_role(accounts(2), Role::Resolver);
+        let market_id = create_market(&mut contract);
+
+        testing_env!(context(accounts(2)).block_timestamp(DEADLINE + 1).build());
+        contract.close_market(market_id, false);
+
+        assert_eq!(contract.get_credit(accounts(1)).0, 0);
+        assert_eq!(contract.get_credit(accounts(0)).0, BOND);
+    }
+
+    #[test]
+    fn expired_bond_slashed_by_anyone() {
+        let mut contract = setup();
+        let market_id = create_market(&mut contract);
+
+        testing_env!(context(accounts(3)).block_timestamp(DEADLINE + 1).build());
+        contract.slash_expired_bond(market_id);
+        assert_eq!(contract.get_credit(accounts(0)).0, BOND);
+
+        // Closing late afterwards has no bond left to return.
+        testing_env!(context(accounts(1)).block_timestamp(DEADLINE + 1).build());
+        contract.close_market(market_id, true);
+        assert_eq!(contract.get_credit(accounts(1)).0, 0);
+        assert_eq!(contract.get_credit(accounts(0)).0, BOND);
+    }
+
+    #[test]
+    #[should_panic(expected = "Market has not passed its resolution deadline.")]
+    fn bond_not_slashed_before_deadline() {
+        let mut contract = setup();
+        let market_id = create_market(&mut contract);
+
+        testing_env!(context(accounts(3)).block_timestamp(DEADLINE).build());
+        contract.slash_expired_bond(market_id);
     }
 
+    #[test]
+    fn bond_slashed_on_invalidation() {
+        let mut contract = setup();
+        contract.grant_role(accounts(2), Role::Moderator);
+        let market_id = create_market(&mut contract);
 
+        testing_env!(context(accounts(2)).block_timestamp(DEADLINE / 2).build());
+        contract.invalidate_market(market_id);
+
+        assert_eq!(contract.get_credit(accounts(1)).0, 0);
+        assert_eq!(contract.get_credit(accounts(0)).0, BOND);
+        assert_eq!(
+            contract.get_market(market_id).unwrap().status,
+            MarketStatus::Closed
+        );
+    }
+
+    #[test]
+    fn match_mints_outcome_tokens() {
+        let mut contract = setup();
+        let market_id = matched_market(&mut contract);
+
+        let market = contract.get_market(market_id).unwrap();
+        assert_eq!(market.shares, 0);
+        assert_eq!(market.stats.volume.0, 200);
+        assert_eq!(market.stats.open_interest.0, 200);
+        assert_eq!(market.stats.long_liquidity.0, 0);
+        assert!(stored_market(&contract, market_id).unminted.is_empty());
+    }
+
+    #[test]
+    fn unminted_tokens_paid_out_on_close() {
+        let mut contract = setup();
+        let market_id = matched_market(&mut contract);
+
+        testing_env!(context(accounts(0)).build());
+        let failed = Err(PromiseError::Failed);
+        assert!(!contract.on_outcome_tokens_minted(
+            market_id,
+            accounts(2),
+            true,
+            200.into(),
+            failed
+        ));
+        assert_eq!(
+            contract.get_credit(accounts(2)).0,
+            OUTCOME_TOKEN_REGISTRATION
+        );
+        assert_eq!(stored_market(&contract, market_id).unminted.len(), 1);
+
+        testing_env!(context(accounts(1)).build());
+        contract.close_market(market_id, true);
+
+        assert_eq!(
+            contract.get_credit(accounts(2)).0,
+            OUTCOME_TOKEN_REGISTRATION + 198
+        );
+        assert_eq!(contract.get_credit(accounts(0)).0, 2);
+        assert_eq!(open_interest(&contract, market_id), 0);
+    }
+
+    #[test]
+    fn winning_tokens_redeemed_minus_fee() {
+        let mut contract = setup();
+        let market_id = matched_market(&mut contract);
+        testing_env!(context(accounts(1)).build());
+        contract.close_market(market_id, true);
+
+        redeem(&mut contract, market_id, true, accounts(2), 150);
+
+        assert_eq!(contract.get_credit(accounts(2)).0, 149);
+        assert_eq!(contract.get_credit(accounts(0)).0, 1);
+        assert_eq!(open_interest(&contract, market_id), 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the winning outcome can be redeemed.")]
+    fn losing_tokens_not_redeemed() {
+        let mut contract = setup();
+        let market_id = matched_market(&mut contract);
+        testing_env!(context(accounts(1)).build());
+        contract.close_market(market_id, true);
+
+        redeem(&mut contract, market_id, false, accounts(3), 200);
+    }
+
+    #[test]
+    fn invalidated_tokens_redeemed_at_half_value() {
+        let mut contract = setup();
+        let market_id = matched_market(&mut contract);
+        testing_env!(context(accounts(0)).build());
+        contract.grant_role(accounts(0), Role::Moderator);
+        contract.invalidate_market(market_id);
+
+        redeem(&mut contract, market_id, true, accounts(2), 200);
+        redeem(&mut contract, market_id, false, accounts(3), 200);
+
+        assert_eq!(contract.get_credit(accounts(2)).0, 100);
+        assert_eq!(contract.get_credit(accounts(3)).0, 100);
+        assert_eq!(open_interest(&contract, market_id), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough collateral left in the market.")]
+    fn redemptions_capped_by_pool() {
+        let mut contract = setup();
+        let market_id = matched_market(&mut contract);
+        testing_env!(context(accounts(1)).build());
+        contract.close_market(market_id, true);
+
+        redeem(&mut contract, market_id, true, accounts(2), 200);
+        redeem(&mut contract, market_id, true, accounts(2), 1);
+    }
+
+    #[test]
+    fn close_refunds_resting_offers() {
+        let mut contract = setup();
+        let market_id = create_market(&mut contract);
+        create_offer(&mut contract, market_id, accounts(2), true, 100);
+
+        testing_env!(context(accounts(1)).build());
+        contract.close_market(market_id, false);
+
+        assert_eq!(
+            contract.get_credit(accounts(2)).0,
+            100 + OUTCOME_TOKEN_REGISTRATION
+        );
+        assert!(contract.get_offers(market_id, None, None).is_empty());
+        assert!(contract
+            .get_offers_by_account(accounts(2), None, None)
+            .is_empty());
+        assert_eq!(
+            stored_market(&contract, market_id).stats.long_liquidity.0,
+            0
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Offer does not exist.")]
+    fn offer_not_accepted_after_resolution() {
+        let mut contract = setup();
+        let market_id = create_market(&mut contract);
+        let offer_id = create_offer(&mut contract, market_id, accounts(2), false, 100);
+        testing_env!(context(accounts(1)).build());
+        contract.close_market(market_id, true);
+
+        accept_offer(&mut contract, offer_id, accounts(3), 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "Market is closed.")]
+    fn offer_not_created_after_resolution() {
+        let mut contract = setup();
+        let market_id = create_market(&mut contract);
+        testing_env!(context(accounts(1)).build());
+        contract.close_market(market_id, true);
+
+        create_offer(&mut contract, market_id, accounts(2), true, 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "Market still has collateral to redeem.")]
+    fn outcome_tokens_not_handed_off_before_redemption() {
+        let mut contract = setup();
+        let market_id = matched_market(&mut contract);
+        testing_env!(context(accounts(1)).build());
+        contract.close_market(market_id, true);
+
+        testing_env!(context(accounts(0)).build());
+        contract.propose_outcome_token_owner(market_id, Some(accounts(4)));
+    }
+
+    #[test]
+    fn outcome_tokens_handed_off_once_redeemed() {
+        let mut contract = setup();
+        let market_id = matched_market(&mut contract);
+        testing_env!(context(accounts(1)).build());
+        contract.close_market(market_id, true);
+        redeem(&mut contract, market_id, true, accounts(2), 200);
+
+        testing_env!(context(accounts(0)).build());
+        contract.propose_outcome_token_owner(market_id, Some(accounts(4)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Market still has collateral to redeem.")]
+    fn outcome_tokens_not_handed_off_while_open() {
+        let mut contract = setup();
+        let market_id = create_market(&mut contract);
+
+        testing_env!(context(accounts(0)).build());
+        contract.propose_outcome_token_owner(market_id, Some(accounts(4)));
+    }
 }
@@ -0,0 +1,419 @@
+//! State versioning and self-upgrade.
+//!
+//! The layout of [`Contract`] is tagged by a version number stored under
+//! [`StorageKey::StateVersion`]. State written before versioning existed has
+//! no tag and is read as [`ContractV0`].
+//!
+//! Markets and offers are each stored as a [`VersionedMarket`] or
+//! [`VersionedOffer`], so that a later change to either only adds a variant
+//! instead of a new contract layout.
+use near_sdk::{serde_json::json, Gas, IntoStorageKey};
+
+use super::*;
+
+/// Layout version written by this code.
+pub const STATE_VERSION: u8 = 1;
+
+const GAS_FOR_MIGRATE: Gas = Gas(100_000_000_000_000);
+
+pub(crate) fn read_state_version() -> Option<u8> {
+    env::storage_read(&StorageKey::StateVersion.into_storage_key()).map(|v| v[0])
+}
+
+pub(crate) fn write_state_version() {
+    env::storage_write(
+        &StorageKey::StateVersion.into_storage_key(),
+        &[STATE_VERSION],
+    );
+}
+
+/// [`Market`] as stored before versioning.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct MarketV0 {
+    id: u32,
+    is_open: bool,
+    description: String,
+    owner: AccountId,
+    shares: Vector<SharePair>,
+    token_contract: AccountId,
+}
+
+impl From<MarketV0> for Market {
+    fn from(m: MarketV0) -> Self {
+        Self {
+            id: m.id,
+            is_open: m.is_open,
+            description: m.description,
+            owner: m.owner,
+            shares: m.shares,
+            offers: UnorderedSet::new(StorageKey::MarketOffers(m.id)),
+            token_contract: m.token_contract,
+            created_at: 0,
+            outcome: None,
+            stats: MarketStats::default(),
+            participants: LookupSet::new(StorageKey::MarketParticipants(m.id)),
+            trades: Vector::new(StorageKey::MarketTrades(m.id)),
+            trade_count: 0,
+            bond: 0.into(),
+            resolution_deadline: u64::MAX,
+            metadata: MarketMetadata {
+                category: String::new(),
+                tags: vec![],
+                rules: String::new(),
+                resolution_source: None,
+                reference: None,
+                reference_hash: None,
+                end_date: u64::MAX.into(),
+            },
+            outcome_tokens: false,
+            unminted: Vector::new(StorageKey::MarketUnminted(m.id)),
+        }
+    }
+}
+
+/// [`Contract`] as stored before versioning.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct ContractV0 {
+    next_offer_id: u32,
+    markets: Vector<MarketV0>,
+    credit: LookupMap<AccountId, u128>,
+    offers: UnorderedMap<u32, Offer>,
+}
+
+/// Progress of a migration from [`ContractV0`]. Markets are converted first,
+/// then every offer is moved to [`Contract::offers`] and indexed on its
+/// market and maker.
+#[derive(BorshSerialize, BorshDeserialize)]
+pub struct Migration {
+    /// Markets in the old layout. It shares its prefix with
+    /// [`Contract::markets`], so entries below `markets.len()` have already
+    /// been overwritten by their converted form.
+    legacy_markets: Vector<MarketV0>,
+    /// Offers in the old layout. Each is removed as it is moved.
+    legacy_offers: UnorderedMap<u32, Offer>,
+    /// Lowest offer id not moved yet.
+    next_offer: u32,
+}
+
+impl ContractV0 {
+    /// Only touches the root state; the per-market work is left to
+    /// [`Contract::migrate_batch`].
+    fn into_current(self, owner_id: AccountId) -> Contract {
+        let mut contract = Contract {
+            next_offer_id: self.next_offer_id,
+            markets: Vector::new(StorageKey::Markets),
+            open_markets: UnorderedSet::new(StorageKey::OpenMarkets),
+            closed_markets: Vector::new(StorageKey::ClosedMarkets),
+            credit: self.credit,
+            offers: UnorderedMap::new(StorageKey::VersionedOffers),
+            account_offers: LookupMap::new(StorageKey::AccountOfferIndex),
+            paused_operations: UnorderedSet::new(StorageKey::PausedOperations),
+            open_market_creation: true,
+            fee_bps: 0,
+            treasury: owner_id.clone(),
+            creator_bond: 0,
+            markets_by_category: UnorderedMap::new(StorageKey::Categories),
+            markets_by_tag: UnorderedMap::new(StorageKey::Tags),
+            reward_config: None,
+            epoch_rewards: LookupMap::new(StorageKey::EpochRewards),
+            maker_rewards: LookupMap::new(StorageKey::MakerRewards),
+            stakes: LookupMap::new(StorageKey::Stakes),
+            discount_tiers: vec![],
+            unstake_cooldown: DEFAULT_UNSTAKE_COOLDOWN,
+            min_offer_amount: 0,
+            allowed_token_contracts: UnorderedSet::new(StorageKey::AllowedTokenContracts),
+            governance_config: GovernanceConfig::default(),
+            proposals: Vector::new(StorageKey::Proposals),
+            votes: LookupMap::new(StorageKey::Votes),
+            snapshot_id: 0,
+            power_checkpoints: LookupMap::new(StorageKey::PowerCheckpoints),
+            migration: Some(Migration {
+                legacy_markets: self.markets,
+                legacy_offers: self.offers,
+                next_offer: 0,
+            }),
+        };
+
+        Owner::init(&mut contract, &owner_id);
+        contract
+    }
+}
+
+/// Every layout [`Contract`] state may be found in.
+pub enum VersionedContract {
+    V0(ContractV0),
+    V1(Contract),
+}
+
+impl VersionedContract {
+    fn read() -> Self {
+        match read_state_version() {
+            None => Self::V0(
+                env::state_read().unwrap_or_else(|| env::panic_str("No state to migrate.")),
+            ),
+            Some(STATE_VERSION) => Self::V1(
+                env::state_read().unwrap_or_else(|| env::panic_str("No state to migrate.")),
+            ),
+            Some(v) => env::panic_str(&format!("Unknown state version {v}.")),
+        }
+    }
+}
+
+impl Contract {
+    /// Panics until [`Contract::migrate_batch`] has finished.
+    pub(crate) fn require_migrated(&self) {
+        require!(self.migration.is_none(), "State migration is in progress.");
+    }
+
+    /// Converts legacy market `id`, which must be the next one.
+    fn migrate_market(&mut self, legacy_markets: &Vector<MarketV0>, id: u32) {
+        // `Vector::get` only lends entries out. A borsh round trip copies the
+        // handle of `shares` without touching its contents.
+        let legacy = legacy_markets
+            .get(id)
+            .map(|m| MarketV0::try_from_slice(&m.try_to_vec().unwrap()).unwrap())
+            .unwrap_or_else(|| env::panic_str("Legacy market is missing."));
+
+        if legacy.is_open {
+            self.open_markets.insert(id);
+        } else {
+            self.closed_markets.push(id);
+        }
+        self.markets.push(Market::from(legacy).into());
+    }
+
+    /// Moves offer `id`, if it is still open, out of `legacy_offers` and adds
+    /// it to the indexes kept since versioning.
+    fn migrate_offer(&mut self, legacy_offers: &mut UnorderedMap<u32, Offer>, id: u32) {
+        let o = match legacy_offers.remove(&id) {
+            Some(o) => o,
+            None => return,
+        };
+
+        if let Some(market) = self
+            .markets
+            .get_mut(o.market_id)
+            .map(VersionedMarket::as_current_mut)
+        {
+            market.offers.insert(id);
+            market.side_liquidity(o.is_long).0 += o.amount.0;
+            market.add_participant(&o.account_id);
+        }
+        self.account_offers
+            .entry(o.account_id.clone())
+            .or_insert_with(|| UnorderedSet::new(StorageKey::AccountOffers(o.account_id.clone())))
+            .insert(id);
+        self.offers.insert(id, o.into());
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Deploys the contract code passed as the raw call input, then calls
+    /// [`Contract::migrate`] on it. Owner only.
+    pub fn upgrade(&self) -> Promise {
+        Self::require_owner();
+        let code = env::input().unwrap_or_else(|| env::panic_str("Missing contract code."));
+
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                "migrate".into(),
+                json!({}).to_string().into_bytes(),
+                0,
+                GAS_FOR_MIGRATE,
+            )
+    }
+
+    /// Converts whatever state layout is stored into the current one.
+    ///
+    /// State from before versioning is converted in constant gas: `owner_id`,
+    /// which defaults to the contract account, becomes the owner and
+    /// treasury, and markets and offers are left for
+    /// [`Contract::migrate_batch`]. Until that finishes, only views and
+    /// owner entrypoints are usable.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate(owner_id: Option<AccountId>) -> Self {
+        let contract = match VersionedContract::read() {
+            VersionedContract::V0(old) => {
+                old.into_current(owner_id.unwrap_or_else(env::current_account_id))
+            }
+            VersionedContract::V1(contract) => contract,
+        };
+        write_state_version();
+        contract
+    }
+
+    /// Converts up to `limit` more markets, or once those are done moves
+    /// up to `limit` more offer ids, of a migration started by
+    /// [`Contract::migrate`]. Can be called again after running out of gas.
+    /// Returns whether the migration is complete. Owner only.
+    pub fn migrate_batch(&mut self, limit: Option<u32>) -> bool {
+        Self::require_owner();
+        let mut migration = self
+            .migration
+            .take()
+            .unwrap_or_else(|| env::panic_str("No migration is in progress."));
+        let mut remaining = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT);
+
+        while remaining > 0 && self.markets.len() < migration.legacy_markets.len() {
+            self.migrate_market(&migration.legacy_markets, self.markets.len());
+            remaining -= 1;
+        }
+
+        let mut next_offer = migration.next_offer;
+        while remaining > 0 && next_offer < self.next_offer_id {
+            self.migrate_offer(&mut migration.legacy_offers, next_offer);
+            next_offer += 1;
+            remaining -= 1;
+        }
+
+        let done = self.markets.len() == migration.legacy_markets.len()
+            && next_offer == self.next_offer_id;
+        if !done {
+            self.migration = Some(Migration {
+                next_offer,
+                ..migration
+            });
+        }
+        done
+    }
+
+    /// Whether a migration started by [`Contract::migrate`] is unfinished.
+    pub fn is_migrating(&self) -> bool {
+        self.migration.is_some()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    fn setup() {
+        testing_env!(VMContextBuilder::new()
+            .current_account_id(accounts(0))
+            .predecessor_account_id(accounts(0))
+            .build());
+    }
+
+    /// State as written by the contract before versioning: an open market
+    /// with a matched share and an open offer, and a closed market.
+    fn legacy_state() -> ContractV0 {
+        let mut c = ContractV0 {
+            next_offer_id: 3,
+            markets: Vector::new(StorageKey::Markets),
+            credit: LookupMap::new(StorageKey::Credit),
+            offers: UnorderedMap::new(StorageKey::Offers),
+        };
+
+        let mut shares = Vector::new(StorageKey::MarketShares(0));
+        shares.push(SharePair {
+            long: accounts(1),
+            short: accounts(2),
+            amount: 10.into(),
+        });
+        c.markets.push(MarketV0 {
+            id: 0,
+            is_open: true,
+            description: "Will it rain?".to_string(),
+            owner: accounts(0),
+            shares,
+            token_contract: accounts(3),
+        });
+        c.markets.push(MarketV0 {
+            id: 1,
+            is_open: false,
+            description: "Will it snow?".to_string(),
+            owner: accounts(0),
+            shares: Vector::new(StorageKey::MarketShares(1)),
+            token_contract: accounts(3),
+        });
+
+        // Offer 1 was matched into the share above.
+        c.offers.insert(
+            0,
+            Offer {
+                id: 0,
+                market_id: 0,
+                is_long: true,
+                account_id: accounts(1),
+                amount: 5.into(),
+            },
+        );
+        c.offers.insert(
+            2,
+            Offer {
+                id: 2,
+                market_id: 0,
+                is_long: false,
+                account_id: accounts(4),
+                amount: 7.into(),
+            },
+        );
+        c.credit.insert(accounts(2), 42);
+
+        c
+    }
+
+    fn migrated() -> Contract {
+        env::state_write(&legacy_state());
+        let mut contract = Contract::migrate(None);
+        while !contract.migrate_batch(Some(1)) {}
+        contract
+    }
+
+    #[test]
+    fn migrate_from_unversioned_state() {
+        setup();
+        env::state_write(&legacy_state());
+        assert_eq!(read_state_version(), None);
+
+        let mut contract = Contract::migrate(None);
+
+        assert_eq!(read_state_version(), Some(STATE_VERSION));
+        assert!(contract.is_migrating());
+        assert!(contract.get_market(0).is_none());
+        assert!(!contract.migrate_batch(Some(2)));
+        assert!(contract.get_market(1).is_some());
+        assert!(contract.get_offers(0, None, None).is_empty());
+        assert!(contract.migrate_batch(None));
+        assert!(!contract.is_migrating());
+
+        let market = contract.get_market(0).unwrap();
+        assert_eq!(market.description, "Will it rain?");
+        assert_eq!(market.shares, 1);
+        assert_eq!(market.token_contract, &accounts(3));
+        assert_eq!(market.status, MarketStatus::Open);
+        assert_eq!(market.stats.long_liquidity.0, 5);
+        assert_eq!(market.stats.short_liquidity.0, 7);
+        assert_eq!(
+            contract.get_market(1).unwrap().status,
+            MarketStatus::Closed
+        );
+        let offers = contract.get_offers(0, None, None);
+        assert_eq!(offers.len(), 2);
+        assert_eq!(offers[0].amount.0, 5);
+        assert_eq!(contract.get_offers_by_account(accounts(4), None, None)[0].id, 2);
+        assert_eq!(contract.get_credit(accounts(2)).0, 42);
+        assert_eq!(contract.get_fee(), (0, &accounts(0)));
+    }
+
+    #[test]
+    fn migrate_current_state_is_noop() {
+        setup();
+        let contract = migrated();
+        env::state_write(&contract);
+        drop(contract);
+
+        let contract = Contract::migrate(None);
+
+        assert!(!contract.is_migrating());
+        assert_eq!(contract.get_market(0).unwrap().shares, 1);
+        assert_eq!(contract.get_offers(0, None, None).len(), 2);
+        assert_eq!(contract.get_credit(accounts(2)).0, 42);
+    }
+}
@@ -0,0 +1,247 @@
+//! OMT liquidity-mining rewards for offer makers.
+//!
+//! Time is split into epochs of equal length. Each epoch mints a fixed OMT
+//! budget, shared between makers in proportion to the collateral their
+//! offers got matched with during that epoch. Rewards for an epoch can be
+//! claimed once it has ended.
+use super::*;
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RewardConfig {
+    /// Start of epoch 0.
+    start: U64,
+    epoch_length: U64,
+    /// OMT minted per epoch, captured by each epoch when it sees its first match.
+    budget_per_epoch: U128,
+}
+
+impl RewardConfig {
+    fn epoch_at(&self, timestamp: u64) -> u64 {
+        timestamp.saturating_sub(self.start.0) / self.epoch_length.0
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EpochRewards {
+    /// Maker volume matched during the epoch.
+    volume: U128,
+    /// OMT shared between the epoch's makers.
+    budget: U128,
+}
+
+/// Per-maker reward accounting. Volume is only tracked for the last epoch
+/// the maker traded in; earlier epochs are already folded into `unclaimed`.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone)]
+pub struct MakerRewards {
+    epoch: u64,
+    volume: u128,
+    unclaimed: u128,
+}
+
+impl Contract {
+    fn current_epoch(&self) -> Option<u64> {
+        self.reward_config
+            .as_ref()
+            .map(|c| c.epoch_at(env::block_timestamp()))
+    }
+
+    fn epoch_ended(&self, epoch: u64) -> bool {
+        self.current_epoch().map_or(false, |current| epoch < current)
+    }
+
+    /// Rewards `maker` has earned in epochs that have ended.
+    fn settled_rewards(&self, maker: &MakerRewards) -> u128 {
+        if !self.epoch_ended(maker.epoch) || maker.volume == 0 {
+            return maker.unclaimed;
+        }
+
+        let epoch = self.epoch_rewards.get(&maker.epoch).unwrap();
+        maker.unclaimed + mul_div(epoch.budget.0, maker.volume, epoch.volume.0)
+    }
+
+    /// Credits `amount` of matched collateral to `maker` for the current epoch.
+    pub(crate) fn record_maker_volume(&mut self, maker: &AccountId, amount: u128) {
+        let epoch = match self.current_epoch() {
+            Some(e) => e,
+            None => return,
+        };
+        let budget = self.reward_config.as_ref().unwrap().budget_per_epoch;
+
+        let totals = self.epoch_rewards.entry(epoch).or_insert(EpochRewards {
+            volume: 0.into(),
+            budget,
+        });
+        totals.volume.0 += amount;
+
+        let mut rewards = self
+            .maker_rewards
+            .get(maker)
+            .cloned()
+            .unwrap_or(MakerRewards {
+                epoch,
+                volume: 0,
+                unclaimed: 0,
+            });
+        if rewards.epoch != epoch {
+            rewards = MakerRewards {
+                epoch,
+                volume: 0,
+                unclaimed: self.settled_rewards(&rewards),
+            };
+        }
+        rewards.volume += amount;
+        self.maker_rewards.insert(maker.clone(), rewards);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Starts the reward program. Epoch 0 begins now. Owner only.
+    pub fn start_rewards(&mut self, epoch_length: U64, budget_per_epoch: U128) {
+        Self::require_owner();
+        require!(
+            self.reward_config.is_none(),
+            "Rewards have already been started."
+        );
+        require!(epoch_length.0 > 0, "Epoch length must be nonzero.");
+
+        self.reward_config = Some(RewardConfig {
+            start: env::block_timestamp().into(),
+            epoch_length,
+            budget_per_epoch,
+        });
+    }
+
+    /// Changes the budget of epochs that have not seen a match yet. Owner only.
+    pub fn set_reward_budget(&mut self, budget_per_epoch: U128) {
+        Self::require_owner();
+        let config = self
+            .reward_config
+            .as_mut()
+            .unwrap_or_else(|| env::panic_str("Rewards have not been started."));
+        config.budget_per_epoch = budget_per_epoch;
+    }
+
+    /// Mints all of the caller's rewards from ended epochs.
+    pub fn claim_rewards(&mut self) -> U128 {
+        self.require_active(Operation::ClaimRewards);
+
+        let account_id = env::predecessor_account_id();
+        let rewards = self
+            .maker_rewards
+            .get(&account_id)
+            .cloned()
+            .unwrap_or_else(|| env::panic_str("You have no rewards to claim."));
+        let amount = self.settled_rewards(&rewards);
+        require!(amount > 0, "You have no rewards to claim.");
+
+        let ended = self.epoch_ended(rewards.epoch);
+        self.maker_rewards.insert(
+            account_id.clone(),
+            MakerRewards {
+                epoch: rewards.epoch,
+                // Volume of an ended epoch has just been paid out.
+                volume: if ended { 0 } else { rewards.volume },
+                unclaimed: 0,
+            },
+        );
+
+        self.checkpoint_voting_power(&account_id);
+        self.mint(account_id.clone(), amount, Some("Liquidity mining rewards".into()));
+
+        ContractEvent::RewardsClaimed {
+            account_id,
+            amount: amount.into(),
+        }
+        .emit();
+
+        amount.into()
+    }
+
+    /// OMT `account_id` can claim now.
+    pub fn get_claimable_rewards(&self, account_id: AccountId) -> U128 {
+        self.maker_rewards
+            .get(&account_id)
+            .map_or(0, |r| self.settled_rewards(r))
+            .into()
+    }
+
+    pub fn get_reward_config(&self) -> Option<&RewardConfig> {
+        self.reward_config.as_ref()
+    }
+
+    pub fn get_epoch_rewards(&self, epoch: U64) -> Option<&EpochRewards> {
+        self.epoch_rewards.get(&epoch.0)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    const EPOCH_LENGTH: u64 = 100;
+    const BUDGET: Balance = 1_000;
+
+    fn context(predecessor: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .predecessor_account_id(predecessor);
+        builder
+    }
+
+    /// Rewards started at time 0.
+    fn setup() -> Contract {
+        testing_env!(context(accounts(0)).build());
+        let mut contract = Contract::new(None);
+        contract.start_rewards(EPOCH_LENGTH.into(), BUDGET.into());
+        contract
+    }
+
+    #[test]
+    fn epoch_budget_split_by_volume() {
+        let mut contract = setup();
+        contract.record_maker_volume(&accounts(1), 300);
+        contract.record_maker_volume(&accounts(2), 100);
+        assert_eq!(contract.get_claimable_rewards(accounts(1)).0, 0);
+
+        testing_env!(context(accounts(1)).block_timestamp(EPOCH_LENGTH).build());
+        assert_eq!(contract.get_claimable_rewards(accounts(1)).0, 750);
+        assert_eq!(contract.get_claimable_rewards(accounts(2)).0, 250);
+        assert_eq!(contract.claim_rewards().0, 750);
+        assert_eq!(Contract::balance_of(&accounts(1)), 750);
+        assert_eq!(contract.get_claimable_rewards(accounts(1)).0, 0);
+    }
+
+    #[test]
+    fn rewards_accrue_across_epochs() {
+        let mut contract = setup();
+        contract.record_maker_volume(&accounts(1), 300);
+        contract.record_maker_volume(&accounts(2), 100);
+
+        testing_env!(context(accounts(0)).block_timestamp(EPOCH_LENGTH).build());
+        contract.record_maker_volume(&accounts(1), 100);
+
+        testing_env!(context(accounts(1))
+            .block_timestamp(2 * EPOCH_LENGTH)
+            .build());
+        assert_eq!(contract.claim_rewards().0, 750 + BUDGET);
+    }
+
+    #[test]
+    #[should_panic(expected = "You have no rewards to claim.")]
+    fn rewards_not_claimed_before_epoch_ends() {
+        let mut contract = setup();
+        contract.record_maker_volume(&accounts(1), 300);
+
+        testing_env!(context(accounts(1))
+            .block_timestamp(EPOCH_LENGTH - 1)
+            .build());
+        contract.claim_rewards();
+    }
+}
@@ -0,0 +1,232 @@
+//! OMT staking for fee discounts.
+//!
+//! Staked OMT is held by the contract's own account. Unstaking starts a
+//! cooldown, after which the tokens can be withdrawn; tokens in cooldown no
+//! longer count towards a discount.
+use super::*;
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct DiscountTier {
+    /// Stake needed to reach this tier.
+    min_stake: U128,
+    /// Share of the fee waived, in basis points.
+    discount_bps: u16,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StakeInfo {
+    staked: U128,
+    unstaking: U128,
+    /// When `unstaking` can be withdrawn.
+    unlock_at: U64,
+}
+
+impl Contract {
+    /// Discount `account_id` gets on fees, in basis points.
+    pub(crate) fn fee_discount_bps(&self, account_id: &AccountId) -> u16 {
+        let staked = self.stakes.get(account_id).map_or(0, |s| s.staked.0);
+
+        self.discount_tiers
+            .iter()
+            .filter(|t| staked >= t.min_stake.0)
+            .map(|t| t.discount_bps)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Fee charged to `account_id` on `amount`, after their staking discount.
+    pub(crate) fn fee_for(&self, account_id: &AccountId, amount: u128) -> u128 {
+        let fee = amount * self.fee_bps as u128 / FEE_DENOMINATOR;
+        fee - fee * self.fee_discount_bps(account_id) as u128 / FEE_DENOMINATOR
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Locks `amount` of the caller's OMT.
+    pub fn stake_omt(&mut self, amount: U128) {
+        require!(amount.0 > 0, "You must stake a nonzero amount.");
+        let account_id = env::predecessor_account_id();
+
+        self.transfer(
+            account_id.clone(),
+            env::current_account_id(),
+            amount.0,
+            Some("Stake".into()),
+        );
+
+        let stake = self
+            .stakes
+            .entry(account_id.clone())
+            .or_insert(StakeInfo {
+                staked: 0.into(),
+                unstaking: 0.into(),
+                unlock_at: 0.into(),
+            });
+        stake.staked.0 += amount.0;
+
+        ContractEvent::Staked { account_id, amount }.emit();
+    }
+
+    /// Starts the cooldown on `amount` of the caller's stake. Starting another
+    /// unstake restarts the cooldown for everything being unstaked.
+    pub fn unstake_omt(&mut self, amount: U128) {
+        require!(amount.0 > 0, "You must unstake a nonzero amount.");
+        let account_id = env::predecessor_account_id();
+        let cooldown = self.unstake_cooldown;
+
+        let stake = self
+            .stakes
+            .get_mut(&account_id)
+            .unwrap_or_else(|| env::panic_str("You have nothing staked."));
+        require!(stake.staked.0 >= amount.0, "Not enough staked.");
+        stake.staked.0 -= amount.0;
+        stake.unstaking.0 += amount.0;
+        stake.unlock_at = (env::block_timestamp() + cooldown).into();
+
+        ContractEvent::Unstaked { account_id, amount }.emit();
+    }
+
+    /// Returns the caller's unstaked OMT once the cooldown is over.
+    pub fn withdraw_unstaked_omt(&mut self) -> U128 {
+        let account_id = env::predecessor_account_id();
+
+        let stake = self
+            .stakes
+            .get_mut(&account_id)
+            .unwrap_or_else(|| env::panic_str("You have nothing staked."));
+        require!(stake.unstaking.0 > 0, "You have nothing to withdraw.");
+        require!(
+            env::block_timestamp() >= stake.unlock_at.0,
+            "Your unstaked tokens are still cooling down."
+        );
+        let amount = std::mem::replace(&mut stake.unstaking, 0.into());
+        if stake.staked.0 == 0 {
+            self.stakes.remove(&account_id);
+        }
+
+        self.transfer(
+            env::current_account_id(),
+            account_id,
+            amount.0,
+            Some("Unstake".into()),
+        );
+
+        amount
+    }
+
+    pub fn get_stake(&self, account_id: AccountId) -> Option<&StakeInfo> {
+        self.stakes.get(&account_id)
+    }
+
+    pub fn get_fee_discount(&self, account_id: AccountId) -> u16 {
+        self.fee_discount_bps(&account_id)
+    }
+
+    /// Replaces the discount tiers. [`Role::FeeManager`] only.
+    pub fn set_discount_tiers(&mut self, tiers: Vec<DiscountTier>) {
+        Self::require_role(&Role::FeeManager);
+        for tier in &tiers {
+            require!(
+                (tier.discount_bps as u128) <= FEE_DENOMINATOR,
+                "Discount cannot exceed 100%."
+            );
+        }
+        self.discount_tiers = tiers;
+    }
+
+    pub fn get_discount_tiers(&self) -> &Vec<DiscountTier> {
+        &self.discount_tiers
+    }
+
+    /// Sets how long unstaked OMT stays locked, in nanoseconds. Owner only.
+    pub fn set_unstake_cooldown(&mut self, cooldown: U64) {
+        Self::require_owner();
+        self.unstake_cooldown = cooldown.0;
+    }
+
+    pub fn get_unstake_cooldown(&self) -> U64 {
+        self.unstake_cooldown.into()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    const OMT: Balance = 10u128.pow(24);
+
+    fn context(predecessor: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(accounts(0))
+            .predecessor_account_id(predecessor);
+        builder
+    }
+
+    /// A 1% fee, halved for stakes of 500 OMT or more, and 1,000 OMT owned by
+    /// `accounts(1)`.
+    fn setup() -> Contract {
+        testing_env!(context(accounts(0)).build());
+        let mut contract = Contract::new(None);
+        contract.grant_role(accounts(0), Role::FeeManager);
+        contract.set_fee(100, None);
+        contract.set_discount_tiers(vec![DiscountTier {
+            min_stake: (500 * OMT).into(),
+            discount_bps: 5_000,
+        }]);
+        contract.mint(accounts(1), 1_000 * OMT, None);
+        contract
+    }
+
+    #[test]
+    fn stake_earns_fee_discount() {
+        let mut contract = setup();
+        assert_eq!(contract.fee_for(&accounts(1), 10_000), 100);
+
+        testing_env!(context(accounts(1)).build());
+        contract.stake_omt((500 * OMT).into());
+
+        assert_eq!(Contract::balance_of(&accounts(1)), 500 * OMT);
+        assert_eq!(contract.get_fee_discount(accounts(1)), 5_000);
+        assert_eq!(contract.fee_for(&accounts(1), 10_000), 50);
+    }
+
+    #[test]
+    fn unstaked_omt_withdrawn_after_cooldown() {
+        let mut contract = setup();
+        testing_env!(context(accounts(1)).build());
+        contract.stake_omt((500 * OMT).into());
+        contract.unstake_omt((200 * OMT).into());
+
+        // Unstaking tokens no longer earn a discount, but still vote.
+        assert_eq!(contract.get_fee_discount(accounts(1)), 0);
+        assert_eq!(contract.get_voting_power(accounts(1)).0, 1_000 * OMT);
+
+        testing_env!(context(accounts(1))
+            .block_timestamp(DEFAULT_UNSTAKE_COOLDOWN)
+            .build());
+        assert_eq!(contract.withdraw_unstaked_omt().0, 200 * OMT);
+        assert_eq!(Contract::balance_of(&accounts(1)), 700 * OMT);
+        assert_eq!(contract.get_stake(accounts(1)).unwrap().staked.0, 300 * OMT);
+    }
+
+    #[test]
+    #[should_panic(expected = "Your unstaked tokens are still cooling down.")]
+    fn unstaked_omt_locked_during_cooldown() {
+        let mut contract = setup();
+        testing_env!(context(accounts(1)).build());
+        contract.stake_omt((500 * OMT).into());
+        contract.unstake_omt((200 * OMT).into());
+
+        testing_env!(context(accounts(1))
+            .block_timestamp(DEFAULT_UNSTAKE_COOLDOWN - 1)
+            .build());
+        contract.withdraw_unstaked_omt();
+    }
+}
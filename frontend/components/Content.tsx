@@ -20,7 +20,7 @@ const Content: React.FC = () => {
       request_type: 'call_function',
       account_id: CONTRACT_ID,
       method_name: 'list_markets',
-      args_base64: '',
+      args_base64: Buffer.from(JSON.stringify({})).toString('base64'),
       finality: 'optimistic',
     });
 
@@ -4,15 +4,20 @@ import { AccountId } from './AccountId';
 import { formatNearAmount } from 'near-api-js/lib/utils/format';
 import { useWalletSelector } from '@/contexts/WalletSelectorContext';
 import { utils } from 'near-api-js';
-import { CONTRACT_ID } from '@/constants';
+import { CONTRACT_ID, OUTCOME_TOKEN_REGISTRATION } from '@/constants';
 
 interface PreviewOfferProps {
   offer: Offer;
+  /** Whether the offer's market mints outcome tokens on a match. */
+  outcomeTokens: boolean;
 }
 
 const PLENTY_OF_GAS = utils.format.parseNearAmount('0.00000000003')!;
 
-export const PreviewOffer: React.FC<PreviewOfferProps> = ({ offer }) => {
+export const PreviewOffer: React.FC<PreviewOfferProps> = ({
+  offer,
+  outcomeTokens,
+}) => {
   const { selector } = useWalletSelector();
 
   const acceptOffer = useCallback(async () => {
@@ -23,7 +28,10 @@ export const PreviewOffer: React.FC<PreviewOfferProps> = ({ offer }) => {
         {
           type: 'FunctionCall',
           params: {
-            deposit: offer.amount,
+            deposit: (
+              BigInt(offer.amount) +
+              (outcomeTokens ? BigInt(OUTCOME_TOKEN_REGISTRATION) : BigInt(0))
+            ).toString(),
             gas: PLENTY_OF_GAS,
             args: { offer_id: offer.id },
             methodName: 'accept_offer',
@@ -31,7 +39,7 @@ export const PreviewOffer: React.FC<PreviewOfferProps> = ({ offer }) => {
         },
       ],
     });
-  }, [offer, selector]);
+  }, [offer, outcomeTokens, selector]);
 
   return (
     <div
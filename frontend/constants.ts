@@ -1 +1,5 @@
 export const CONTRACT_ID = process.env.NEXT_PUBLIC_CONTRACT_ID!;
+
+// Deposit that registers each side of a match with its outcome token; the
+// contract credits back whatever the token does not use.
+export const OUTCOME_TOKEN_REGISTRATION = '1250000000000000000000';
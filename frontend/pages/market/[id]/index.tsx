@@ -92,8 +92,14 @@ export default function Market() {
       </div>
       <h3 className="my-3 text-lg font-semibold">Open Offers</h3>
       <div>
-        {offers.length
-          ? offers.map((offer) => <PreviewOffer key={offer.id} offer={offer} />)
+        {market && offers.length
+          ? offers.map((offer) => (
+              <PreviewOffer
+                key={offer.id}
+                offer={offer}
+                outcomeTokens={market.outcome_tokens}
+              />
+            ))
           : 'None'}
       </div>
     </>
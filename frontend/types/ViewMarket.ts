@@ -4,4 +4,30 @@ export default interface ViewMarket {
   description: string;
   owner: string;
   shares: number;
+  token_contract: string;
+  created_at: string;
+  status: 'open' | 'closed' | 'resolved';
+  stats: MarketStats;
+  bond: string;
+  resolution_deadline: string;
+  metadata: MarketMetadata;
+  outcome_tokens: boolean;
+}
+
+export interface MarketMetadata {
+  category: string;
+  tags: string[];
+  rules: string;
+  resolution_source: string | null;
+  reference: string | null;
+  reference_hash: string | null;
+  end_date: string;
+}
+
+export interface MarketStats {
+  volume: string;
+  open_interest: string;
+  long_liquidity: string;
+  short_liquidity: string;
+  participants: number;
 }
@@ -0,0 +1,190 @@
+//! Opt-in balance checkpoints.
+//!
+//! Once enabled, every change to an account's balance or to the total supply
+//! records `(block height, value after the change)`. The first change for an
+//! account also records its value at the block checkpointing was enabled in,
+//! so every balance since then can be looked up.
+//!
+//! Checkpoints are kept in one indexed vector per account, plus one for the
+//! supply, so recording one writes a single entry. The storage they take up
+//! is paid from a fund the owner tops up with `fund_checkpoint_storage`, so
+//! that transfers keep working without any extra deposit from their callers.
+use near_sdk::StorageUsage;
+
+use super::*;
+
+/// Last value recorded at or before `block_height`.
+fn value_at(checkpoints: &Vector<(u64, Balance)>, block_height: u64) -> Option<Balance> {
+    // Binary search for the number of checkpoints at or before `block_height`.
+    let (mut low, mut high) = (0, checkpoints.len());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if checkpoints.get(mid).unwrap().0 <= block_height {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low.checked_sub(1)
+        .and_then(|i| checkpoints.get(i))
+        .map(|(_, value)| value)
+}
+
+/// Appends `(block_height, value)`, replacing a checkpoint from the same block.
+fn push_checkpoint(checkpoints: &mut Vector<(u64, Balance)>, block_height: u64, value: Balance) {
+    match checkpoints.len().checked_sub(1) {
+        Some(last) if checkpoints.get(last).unwrap().0 == block_height => {
+            checkpoints.replace(last, &(block_height, value));
+        }
+        _ => checkpoints.push(&(block_height, value)),
+    }
+}
+
+impl Contract {
+    /// Runs `f`, which may change the balances of `accounts` and the total
+    /// supply, and checkpoints whatever it changed.
+    pub(crate) fn with_checkpoints<R>(
+        &mut self,
+        accounts: &[&AccountId],
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        if self.checkpoints_since.is_none() {
+            return f(self);
+        }
+
+        let previous = accounts
+            .iter()
+            .map(|a| self.token.accounts.get(a).unwrap_or(0))
+            .collect::<Vec<_>>();
+        let previous_supply = self.token.total_supply;
+        let result = f(self);
+
+        let storage_before = env::storage_usage();
+        for (account_id, previous) in accounts.iter().zip(previous) {
+            self.record_balance_change(account_id, previous);
+        }
+        self.record_supply_change(previous_supply);
+        self.charge_checkpoint_storage(storage_before);
+        result
+    }
+
+    /// Checkpoints the balance of `account_id`, which was `previous` before
+    /// the current change.
+    pub(crate) fn record_balance_change(&mut self, account_id: &AccountId, previous: Balance) {
+        let since = match self.checkpoints_since {
+            Some(since) => since,
+            None => return,
+        };
+        let balance = self.token.accounts.get(account_id).unwrap_or(0);
+        if balance == previous {
+            return;
+        }
+
+        let mut checkpoints = self.balance_checkpoints.get(account_id).unwrap_or_else(|| {
+            Vector::new(StorageKey::AccountCheckpoints {
+                account_hash: env::sha256(account_id.as_bytes()),
+            })
+        });
+        if checkpoints.is_empty() {
+            checkpoints.push(&(since, previous));
+        }
+        push_checkpoint(&mut checkpoints, env::block_height(), balance);
+        self.balance_checkpoints.insert(account_id, &checkpoints);
+    }
+
+    pub(crate) fn record_supply_change(&mut self, previous: Balance) {
+        let since = match self.checkpoints_since {
+            Some(since) => since,
+            None => return,
+        };
+        let supply = self.token.total_supply;
+        if supply == previous {
+            return;
+        }
+
+        if self.supply_checkpoints.is_empty() {
+            self.supply_checkpoints.push(&(since, previous));
+        }
+        push_checkpoint(&mut self.supply_checkpoints, env::block_height(), supply);
+    }
+
+    /// Pays for the storage used since `storage_before` from the checkpoint
+    /// storage fund. Once the fund runs dry, the contract's own balance
+    /// covers the rest until the owner tops it up again.
+    pub(crate) fn charge_checkpoint_storage(&mut self, storage_before: StorageUsage) {
+        let used = env::storage_usage().saturating_sub(storage_before);
+        let cost = used as Balance * env::storage_byte_cost();
+        self.checkpoint_storage = self.checkpoint_storage.saturating_sub(cost);
+    }
+
+    fn assert_checkpointed(&self, block_height: u64) {
+        let since = self
+            .checkpoints_since
+            .unwrap_or_else(|| env::panic_str("Checkpoints are not enabled."));
+        require!(
+            block_height >= since,
+            format!("Checkpoints only go back to block {}.", since)
+        );
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Starts recording balance checkpoints from the current block on. This
+    /// cannot be undone. Owner only.
+    pub fn enable_checkpoints(&mut self) {
+        self.assert_owner_calling();
+        require!(
+            self.checkpoints_since.is_none(),
+            "Checkpoints are already enabled."
+        );
+        self.checkpoints_since = Some(env::block_height());
+    }
+
+    /// Block checkpoints have been recorded since, if enabled.
+    pub fn get_checkpoints_since(&self) -> Option<U64> {
+        self.checkpoints_since.map(U64)
+    }
+
+    /// Adds the attached deposit to the checkpoint storage fund. Owner only.
+    #[payable]
+    pub fn fund_checkpoint_storage(&mut self) -> U128 {
+        self.assert_owner_calling();
+        self.checkpoint_storage += env::attached_deposit();
+        self.checkpoint_storage.into()
+    }
+
+    /// Returns `amount` of the checkpoint storage fund to the owner. Owner only.
+    pub fn withdraw_checkpoint_storage(&mut self, amount: U128) -> Promise {
+        self.assert_owner_calling();
+        require!(
+            self.checkpoint_storage >= amount.0,
+            "Not enough in the checkpoint storage fund."
+        );
+        self.checkpoint_storage -= amount.0;
+        Promise::new(env::predecessor_account_id()).transfer(amount.0)
+    }
+
+    /// What is left of the checkpoint storage fund.
+    pub fn get_checkpoint_storage(&self) -> U128 {
+        self.checkpoint_storage.into()
+    }
+
+    /// Balance of `account_id` at the end of block `block_height`.
+    pub fn ft_balance_of_at(&self, account_id: AccountId, block_height: U64) -> U128 {
+        self.assert_checkpointed(block_height.0);
+        self.balance_checkpoints
+            .get(&account_id)
+            .and_then(|checkpoints| value_at(&checkpoints, block_height.0))
+            .unwrap_or_else(|| self.token.accounts.get(&account_id).unwrap_or(0))
+            .into()
+    }
+
+    /// Total supply at the end of block `block_height`.
+    pub fn ft_total_supply_at(&self, block_height: U64) -> U128 {
+        self.assert_checkpointed(block_height.0);
+        value_at(&self.supply_checkpoints, block_height.0)
+            .unwrap_or(self.token.total_supply)
+            .into()
+    }
+}
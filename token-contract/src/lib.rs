@@ -18,29 +18,75 @@ NOTES:
 use near_contract_standards::fungible_token::metadata::{
     FungibleTokenMetadata, FungibleTokenMetadataProvider, FT_METADATA_SPEC,
 };
+use near_contract_standards::fungible_token::core::FungibleTokenCore;
+use near_contract_standards::fungible_token::events::{FtBurn, FtMint, FtTransfer};
+use near_contract_standards::fungible_token::resolver::FungibleTokenResolver;
 use near_contract_standards::fungible_token::FungibleToken;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LazyOption;
-use near_sdk::json_types::U128;
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedSet, Vector};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde_json::{json, Value};
 use near_sdk::{
-    env, log, near_bindgen, require, AccountId, Balance, BorshStorageKey, PanicOnDefault,
-    PromiseOrValue,
+    assert_one_yocto, env, log, near_bindgen, require, AccountId, Balance, BorshStorageKey, Gas,
+    PanicOnDefault, Promise, PromiseError, PromiseOrValue,
 };
 
+mod checkpoints;
+mod vesting;
+
+use vesting::VestingSchedule;
+
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
     token: FungibleToken,
     metadata: LazyOption<FungibleTokenMetadata>,
-    owner_id: AccountId,
+    /// `None` once ownership has been renounced.
+    owner_id: Option<AccountId>,
+    /// Account `propose_owner` named, until it accepts.
+    proposed_owner: Option<AccountId>,
+    minters: UnorderedSet<AccountId>,
+    /// NEAR prepaid by each minter to register the accounts it mints to.
+    storage_pools: LookupMap<AccountId, Balance>,
+    /// Hard cap on the total supply, if any.
+    max_supply: Option<Balance>,
+    /// How much each minter other than the owner may still mint.
+    mint_allowances: LookupMap<AccountId, Balance>,
+    /// While set, `ft_transfer` and `ft_transfer_call` are disabled.
+    frozen: bool,
+    /// Block balance checkpointing was enabled in, if it has been.
+    checkpoints_since: Option<u64>,
+    balance_checkpoints: LookupMap<AccountId, Vector<(u64, Balance)>>,
+    supply_checkpoints: Vector<(u64, Balance)>,
+    /// NEAR the owner has set aside to pay for checkpoint storage.
+    checkpoint_storage: Balance,
+    next_vesting_id: u64,
+    vesting_schedules: LookupMap<u64, VestingSchedule>,
+    /// Ids of the vesting schedules of each beneficiary.
+    account_vestings: LookupMap<AccountId, Vec<u64>>,
 }
 
+/// Most entries a single `mint_tokens_batch` or `ft_transfer_batch` call can
+/// take, to stay within gas.
+const MAX_BATCH_SIZE: usize = 100;
+
+const GAS_FOR_REDEEM: Gas = Gas(10_000_000_000_000);
+const GAS_FOR_RESOLVE_REDEEM: Gas = Gas(5_000_000_000_000);
+
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 288 288'%3E%3Cg id='l' data-name='l'%3E%3Cpath d='M187.58,79.81l-30.1,44.69a3.2,3.2,0,0,0,4.75,4.2L191.86,103a1.2,1.2,0,0,1,2,.91v80.46a1.2,1.2,0,0,1-2.12.77L102.18,77.93A15.35,15.35,0,0,0,90.47,72.5H87.34A15.34,15.34,0,0,0,72,87.84V201.16A15.34,15.34,0,0,0,87.34,216.5h0a15.35,15.35,0,0,0,13.08-7.31l30.1-44.69a3.2,3.2,0,0,0-4.75-4.2L96.14,186a1.2,1.2,0,0,1-2-.91V104.61a1.2,1.2,0,0,1,2.12-.77l89.55,107.23a15.35,15.35,0,0,0,11.71,5.43h3.13A15.34,15.34,0,0,0,216,201.16V87.84A15.34,15.34,0,0,0,200.66,72.5h0A15.35,15.35,0,0,0,187.58,79.81Z'/%3E%3C/g%3E%3C/svg%3E";
 
 #[derive(BorshSerialize, BorshStorageKey)]
 enum StorageKey {
     FungibleToken,
     Metadata,
+    Minters,
+    StoragePools,
+    MintAllowances,
+    BalanceCheckpoints,
+    SupplyCheckpoints,
+    AccountCheckpoints { account_hash: Vec<u8> },
+    VestingSchedules,
+    AccountVestings,
 }
 
 #[near_bindgen]
@@ -52,6 +98,7 @@ impl Contract {
         Self::new(
             owner_contract_id,
             total_supply,
+            None,
             FungibleTokenMetadata {
                 spec: FT_METADATA_SPEC.to_string(),
                 name: "Example NEAR fungible token".to_string(),
@@ -65,49 +112,523 @@ impl Contract {
     }
 
     /// Initializes the contract with the given total supply owned by the given `owner_id` with
-    /// the given fungible token metadata.
+    /// the given fungible token metadata. If `max_supply` is set, minting can never take the
+    /// total supply above it.
     #[init]
-    pub fn new(owner_contract_id: AccountId, total_supply: U128, metadata: FungibleTokenMetadata) -> Self {
+    pub fn new(
+        owner_contract_id: AccountId,
+        total_supply: U128,
+        max_supply: Option<U128>,
+        metadata: FungibleTokenMetadata,
+    ) -> Self {
         require!(!env::state_exists(), "Already initialized");
         metadata.assert_valid();
+        if let Some(max_supply) = max_supply {
+            require!(
+                total_supply.0 <= max_supply.0,
+                "Initial supply exceeds the maximum supply."
+            );
+        }
         let mut this = Self {
             token: FungibleToken::new(StorageKey::FungibleToken),
             metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
-            owner_id: owner_contract_id,
+            owner_id: Some(owner_contract_id.clone()),
+            proposed_owner: None,
+            minters: UnorderedSet::new(StorageKey::Minters),
+            storage_pools: LookupMap::new(StorageKey::StoragePools),
+            max_supply: max_supply.map(|s| s.0),
+            mint_allowances: LookupMap::new(StorageKey::MintAllowances),
+            frozen: false,
+            checkpoints_since: None,
+            balance_checkpoints: LookupMap::new(StorageKey::BalanceCheckpoints),
+            supply_checkpoints: Vector::new(StorageKey::SupplyCheckpoints),
+            checkpoint_storage: 0,
+            next_vesting_id: 0,
+            vesting_schedules: LookupMap::new(StorageKey::VestingSchedules),
+            account_vestings: LookupMap::new(StorageKey::AccountVestings),
         };
-        this.token.internal_register_account(&this.owner_id);
-        this.token.internal_deposit(&this.owner_id, total_supply.into());
+        this.minters.insert(&owner_contract_id);
+        this.token.internal_register_account(&owner_contract_id);
+        this.token.internal_deposit(&owner_contract_id, total_supply.into());
+
+        FtMint {
+            owner_id: &owner_contract_id,
+            amount: &total_supply,
+            memo: Some("Initial tokens supply is minted"),
+        }
+        .emit();
         this
     }
 
-    pub fn mint_tokens(&mut self, account_id: AccountId, amount: U128) {
-        self.assert_owner_calling();
-        self.token.internal_deposit(&account_id, amount.into());
+    /// Mints to `account_id`, registering it first if needed. Registration is
+    /// paid from the attached deposit, then from the caller's storage pool.
+    /// Unused deposit is refunded, and returned. Minters other than the owner
+    /// spend their mint allowance.
+    #[payable]
+    pub fn mint_tokens(
+        &mut self,
+        account_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+    ) -> U128 {
+        self.assert_minter_calling();
+        let mut deposit = env::attached_deposit();
+        self.internal_mint(&account_id, amount.into(), &mut deposit);
+        self.refund_deposit(deposit);
+
+        FtMint {
+            owner_id: &account_id,
+            amount: &amount,
+            memo: memo.as_deref(),
+        }
+        .emit();
+        deposit.into()
+    }
+
+    /// Like `mint_tokens`, for many accounts at once.
+    #[payable]
+    pub fn mint_tokens_batch(&mut self, mints: Vec<(AccountId, U128)>, memo: Option<String>) {
+        self.assert_minter_calling();
+        require!(
+            !mints.is_empty() && mints.len() <= MAX_BATCH_SIZE,
+            "Must mint to between 1 and 100 accounts."
+        );
+        let mut deposit = env::attached_deposit();
+        for (account_id, amount) in &mints {
+            self.internal_mint(account_id, amount.0, &mut deposit);
+        }
+        self.refund_deposit(deposit);
+
+        FtMint::emit_many(
+            &mints
+                .iter()
+                .map(|(owner_id, amount)| FtMint {
+                    owner_id,
+                    amount,
+                    memo: memo.as_deref(),
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    fn internal_mint(&mut self, account_id: &AccountId, amount: Balance, deposit: &mut Balance) {
+        Self::assert_not_escrow(account_id);
+        if let Some(max_supply) = self.max_supply {
+            require!(
+                self.token.total_supply + amount <= max_supply,
+                "Minting would exceed the maximum supply."
+            );
+        }
+        let minter = env::predecessor_account_id();
+        if Some(&minter) != self.owner_id.as_ref() {
+            let allowance = self.mint_allowances.get(&minter).unwrap_or(0);
+            require!(allowance >= amount, "Not enough mint allowance.");
+            self.mint_allowances.insert(&minter, &(allowance - amount));
+        }
+
+        if !self.token.accounts.contains_key(account_id) {
+            let cost = self.storage_balance_bounds().min.0;
+            if *deposit >= cost {
+                *deposit -= cost;
+            } else {
+                let pool = self.storage_pools.get(&minter).unwrap_or(0);
+                require!(
+                    pool >= cost,
+                    "Not enough deposit or storage pool to register the receiver."
+                );
+                self.storage_pools.insert(&minter, &(pool - cost));
+            }
+            self.token.internal_register_account(account_id);
+        }
+        self.with_checkpoints(&[account_id], |this| {
+            this.token.internal_deposit(account_id, amount)
+        });
+    }
+
+    fn refund_deposit(&self, amount: Balance) {
+        if amount > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(amount);
+        }
+    }
+
+    /// Adds the attached deposit to the caller's storage pool.
+    #[payable]
+    pub fn deposit_storage_pool(&mut self) -> U128 {
+        self.assert_minter_calling();
+        let minter = env::predecessor_account_id();
+        let pool = self.storage_pools.get(&minter).unwrap_or(0) + env::attached_deposit();
+        self.storage_pools.insert(&minter, &pool);
+        pool.into()
+    }
+
+    /// Returns `amount` from the caller's storage pool.
+    pub fn withdraw_storage_pool(&mut self, amount: U128) -> Promise {
+        let minter = env::predecessor_account_id();
+        let pool = self.storage_pools.get(&minter).unwrap_or(0);
+        require!(pool >= amount.0, "Not enough in the storage pool.");
+        self.storage_pools.insert(&minter, &(pool - amount.0));
+        Promise::new(minter).transfer(amount.0)
+    }
+
+    pub fn get_storage_pool(&self, account_id: AccountId) -> U128 {
+        self.storage_pools.get(&account_id).unwrap_or(0).into()
     }
     
-    pub fn burn_tokens(&mut self, account_id: AccountId, amount: U128) {
-        self.assert_owner_calling();
-        self.token.internal_withdraw(&account_id, amount.into());
+    pub fn burn_tokens(&mut self, account_id: AccountId, amount: U128, memo: Option<String>) {
+        self.assert_minter_calling();
+        self.with_checkpoints(&[&account_id], |this| {
+            this.token.internal_withdraw(&account_id, amount.into())
+        });
+
+        FtBurn {
+            owner_id: &account_id,
+            amount: &amount,
+            memo: memo.as_deref(),
+        }
+        .emit();
     }
     
+    /// Like `ft_transfer`, for many receivers at once. Either every transfer
+    /// goes through or none does. Logs one `ft_transfer` event for all of them.
+    #[payable]
+    pub fn ft_transfer_batch(&mut self, receivers: Vec<(AccountId, U128)>, memo: Option<String>) {
+        assert_one_yocto();
+        self.assert_not_frozen();
+        require!(
+            !receivers.is_empty() && receivers.len() <= MAX_BATCH_SIZE,
+            "Must transfer to between 1 and 100 receivers."
+        );
+        let sender_id = env::predecessor_account_id();
+
+        let accounts = std::iter::once(&sender_id)
+            .chain(receivers.iter().map(|(receiver_id, _)| receiver_id))
+            .collect::<Vec<_>>();
+        self.with_checkpoints(&accounts, |this| {
+            for (receiver_id, amount) in &receivers {
+                Self::assert_not_escrow(receiver_id);
+                require!(&sender_id != receiver_id, "Sender and receiver should be different");
+                require!(amount.0 > 0, "The amount should be a positive number");
+                this.token.internal_withdraw(&sender_id, amount.0);
+                this.token.internal_deposit(receiver_id, amount.0);
+            }
+        });
+
+        FtTransfer::emit_many(
+            &receivers
+                .iter()
+                .map(|(receiver_id, amount)| FtTransfer {
+                    old_owner_id: &sender_id,
+                    new_owner_id: receiver_id,
+                    amount,
+                    memo: memo.as_deref(),
+                })
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    /// Burns `amount` of the caller's tokens and asks the owner, the market
+    /// contract, to pay out the collateral behind them. The tokens are minted
+    /// back if the payout fails. Works while frozen.
+    #[payable]
+    pub fn ft_redeem(&mut self, amount: U128) -> Promise {
+        assert_one_yocto();
+        require!(amount.0 > 0, "The amount should be a positive number");
+        let owner_id = self
+            .owner_id
+            .clone()
+            .unwrap_or_else(|| env::panic_str("Token has no owner to redeem with."));
+        let account_id = env::predecessor_account_id();
+        self.with_checkpoints(&[&account_id], |this| {
+            this.token.internal_withdraw(&account_id, amount.0)
+        });
+
+        FtBurn {
+            owner_id: &account_id,
+            amount: &amount,
+            memo: Some("redeem"),
+        }
+        .emit();
+
+        let args = json!({ "account_id": account_id, "amount": amount });
+        Promise::new(owner_id)
+            .function_call(
+                "redeem_outcome_tokens".into(),
+                args.to_string().into_bytes(),
+                0,
+                GAS_FOR_REDEEM,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_REDEEM)
+                    .on_redeem(account_id, amount),
+            )
+    }
+
+    /// Mints the tokens burned by `ft_redeem` back if the payout failed.
+    #[private]
+    pub fn on_redeem(
+        &mut self,
+        account_id: AccountId,
+        amount: U128,
+        #[callback_result] result: Result<(), PromiseError>,
+    ) -> bool {
+        if result.is_ok() {
+            return true;
+        }
+
+        // The account may have unregistered while the payout was in flight.
+        if !self.token.accounts.contains_key(&account_id) {
+            self.token.internal_register_account(&account_id);
+        }
+        self.with_checkpoints(&[&account_id], |this| {
+            this.token.internal_deposit(&account_id, amount.0)
+        });
+
+        FtMint {
+            owner_id: &account_id,
+            amount: &amount,
+            memo: Some("refund failed redeem"),
+        }
+        .emit();
+        false
+    }
+
+    /// Allows `account_id` to call `mint_tokens` and `burn_tokens`.
+    pub fn add_minter(&mut self, account_id: AccountId) {
+        self.assert_owner_calling();
+        self.minters.insert(&account_id);
+    }
+
+    pub fn remove_minter(&mut self, account_id: AccountId) {
+        self.assert_owner_calling();
+        self.minters.remove(&account_id);
+        self.mint_allowances.remove(&account_id);
+    }
+
+    pub fn get_minters(&self) -> Vec<AccountId> {
+        self.minters.to_vec()
+    }
+
+    /// Adds `amount` to what minter `account_id` may mint. Owner only.
+    pub fn increase_mint_allowance(&mut self, account_id: AccountId, amount: U128) -> U128 {
+        self.assert_owner_calling();
+        require!(
+            self.minters.contains(&account_id),
+            "Account is not a minter."
+        );
+        let allowance = self.mint_allowances.get(&account_id).unwrap_or(0) + amount.0;
+        self.mint_allowances.insert(&account_id, &allowance);
+        allowance.into()
+    }
+
+    /// How much `account_id` may still mint. The owner is not limited by an allowance.
+    pub fn get_mint_allowance(&self, account_id: AccountId) -> U128 {
+        self.mint_allowances.get(&account_id).unwrap_or(0).into()
+    }
+
+    pub fn get_max_supply(&self) -> Option<U128> {
+        self.max_supply.map(U128)
+    }
+
+    /// Replaces the token metadata, e.g. to fix an icon or reference. Owner only.
+    pub fn set_metadata(&mut self, metadata: FungibleTokenMetadata) {
+        self.assert_owner_calling();
+        metadata.assert_valid();
+        self.metadata.set(&metadata);
+    }
+
+    /// Blocks transfers, e.g. once the market behind this token has resolved against it.
+    /// Minting and burning keep working. Owner only.
+    pub fn freeze(&mut self) {
+        self.assert_owner_calling();
+        require!(!self.frozen, "Token is already frozen.");
+        self.frozen = true;
+        emit_event("ft_freeze", None);
+    }
+
+    pub fn unfreeze(&mut self) {
+        self.assert_owner_calling();
+        require!(self.frozen, "Token is not frozen.");
+        self.frozen = false;
+        emit_event("ft_unfreeze", None);
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Names `account_id` as the next owner. It takes over once it calls
+    /// `accept_ownership`. Passing `None` withdraws the proposal. Owner only.
+    pub fn propose_owner(&mut self, account_id: Option<AccountId>) {
+        self.assert_owner_calling();
+        self.proposed_owner = account_id;
+        emit_event(
+            "owner_proposed",
+            Some(json!({
+                "old_owner": self.owner_id,
+                "new_owner": self.proposed_owner,
+            })),
+        );
+    }
+
+    /// Makes the caller the owner, if it is the proposed owner. The owner's
+    /// unlimited minting moves along with ownership.
+    pub fn accept_ownership(&mut self) {
+        let new_owner = env::predecessor_account_id();
+        require!(
+            self.proposed_owner.as_ref() == Some(&new_owner),
+            "Only the proposed owner can accept ownership."
+        );
+        self.proposed_owner = None;
+        let old_owner = self.owner_id.replace(new_owner.clone());
+        if let Some(old_owner) = &old_owner {
+            self.minters.remove(old_owner);
+        }
+        self.minters.insert(&new_owner);
+        self.mint_allowances.remove(&new_owner);
+
+        emit_event(
+            "ownership_transferred",
+            Some(json!({
+                "old_owner": old_owner,
+                "new_owner": new_owner,
+            })),
+        );
+    }
+
+    /// Leaves the token without an owner for good. Owner only.
+    pub fn renounce_ownership(&mut self) {
+        self.assert_owner_calling();
+        let old_owner = self.owner_id.take().unwrap();
+        self.proposed_owner = None;
+        self.minters.remove(&old_owner);
+
+        emit_event(
+            "ownership_renounced",
+            Some(json!({ "old_owner": old_owner })),
+        );
+    }
+
+    pub fn get_owner(&self) -> Option<AccountId> {
+        self.owner_id.clone()
+    }
+
+    pub fn get_proposed_owner(&self) -> Option<AccountId> {
+        self.proposed_owner.clone()
+    }
+
+    fn assert_not_frozen(&self) {
+        require!(!self.frozen, "Token is frozen.");
+    }
+
     fn assert_owner_calling(&self) {
         assert_eq!(
-            &env::predecessor_account_id(),
-            &self.owner_id,
+            Some(env::predecessor_account_id()),
+            self.owner_id,
             "Only the owner can call this function."
         );
     }
+
+    fn assert_minter_calling(&self) {
+        assert!(
+            self.minters.contains(&env::predecessor_account_id()),
+            "Only a minter can call this function."
+        );
+    }
     
     fn on_account_closed(&mut self, account_id: AccountId, balance: Balance) {
         log!("Closed @{} with {}", account_id, balance);
+        let storage_before = env::storage_usage();
+        self.record_balance_change(&account_id, balance);
+        self.record_supply_change(self.token.total_supply + balance);
+        self.charge_checkpoint_storage(storage_before);
+        if balance > 0 {
+            FtBurn {
+                owner_id: &account_id,
+                amount: &balance.into(),
+                memo: Some("Account closed with storage_unregister"),
+            }
+            .emit();
+        }
     }
 
     fn on_tokens_burned(&mut self, account_id: AccountId, amount: Balance) {
+        // `ft_resolve_transfer` has already emitted `ft_burn` for these tokens.
         log!("Account @{} burned {}", account_id, amount);
     }
 }
 
-near_contract_standards::impl_fungible_token_core!(Contract, token, on_tokens_burned);
+/// Logs a NEP-297 event, with `data` if given.
+fn emit_event(event: &str, data: Option<Value>) {
+    let mut event = json!({
+        "standard": "x-outcome-token",
+        "version": "1.0.0",
+        "event": event,
+    });
+    if let Some(data) = data {
+        event["data"] = json!([data]);
+    }
+    log!("EVENT_JSON:{}", event);
+}
+
+#[near_bindgen]
+impl FungibleTokenCore for Contract {
+    #[payable]
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        self.assert_not_frozen();
+        Self::assert_not_escrow(&receiver_id);
+        let sender_id = env::predecessor_account_id();
+        self.with_checkpoints(&[&sender_id, &receiver_id.clone()], |this| {
+            this.token.ft_transfer(receiver_id, amount, memo)
+        })
+    }
+
+    #[payable]
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        self.assert_not_frozen();
+        Self::assert_not_escrow(&receiver_id);
+        let sender_id = env::predecessor_account_id();
+        self.with_checkpoints(&[&sender_id, &receiver_id.clone()], |this| {
+            this.token.ft_transfer_call(receiver_id, amount, memo, msg)
+        })
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.token.ft_total_supply()
+    }
+
+    fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        self.token.ft_balance_of(account_id)
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenResolver for Contract {
+    /// Still runs while frozen, so transfers started before the freeze are settled.
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128 {
+        let (used_amount, burned_amount) =
+            self.with_checkpoints(&[&sender_id, &receiver_id.clone()], |this| {
+                this.token.internal_ft_resolve_transfer(&sender_id, receiver_id, amount)
+            });
+        if burned_amount > 0 {
+            self.on_tokens_burned(sender_id, burned_amount);
+        }
+        used_amount.into()
+    }
+}
+
 near_contract_standards::impl_fungible_token_storage!(Contract, token, on_account_closed);
 
 #[near_bindgen]
@@ -119,13 +640,25 @@ impl FungibleTokenMetadataProvider for Contract {
 
 #[cfg(all(test, not(target_arch = "wasm32")))]
 mod tests {
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::test_utils::{accounts, get_logs, VMContextBuilder};
     use near_sdk::{testing_env, Balance};
 
     use super::*;
 
     const TOTAL_SUPPLY: Balance = 1_000_000_000_000_000;
 
+    fn contract_metadata() -> FungibleTokenMetadata {
+        FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "Test token".to_string(),
+            symbol: "TEST".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 24,
+        }
+    }
+
     fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
         builder
@@ -183,4 +716,491 @@ mod tests {
         assert_eq!(contract.ft_balance_of(accounts(2)).0, (TOTAL_SUPPLY - transfer_amount));
         assert_eq!(contract.ft_balance_of(accounts(1)).0, transfer_amount);
     }
+
+    #[test]
+    fn test_added_minter_can_mint() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.add_minter(accounts(2));
+        contract.increase_mint_allowance(accounts(2), 15.into());
+        assert_eq!(contract.get_minters(), vec![accounts(1), accounts(2)]);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.mint_tokens(accounts(1), 10.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY + 10);
+        assert_eq!(contract.get_mint_allowance(accounts(2)).0, 5);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough mint allowance.")]
+    fn test_minter_cannot_exceed_allowance() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.add_minter(accounts(2));
+        contract.increase_mint_allowance(accounts(2), 5.into());
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.mint_tokens(accounts(1), 10.into(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Minting would exceed the maximum supply.")]
+    fn test_mint_capped_by_max_supply() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new(
+            accounts(1),
+            TOTAL_SUPPLY.into(),
+            Some((TOTAL_SUPPLY + 10).into()),
+            contract_metadata(),
+        );
+        assert_eq!(contract.get_max_supply(), Some((TOTAL_SUPPLY + 10).into()));
+
+        contract.mint_tokens(accounts(1), 10.into(), None);
+        contract.mint_tokens(accounts(1), 1.into(), None);
+    }
+
+    #[test]
+    fn test_mint_registers_receiver_from_deposit() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        let cost = contract.storage_balance_bounds().min.0;
+
+        testing_env!(context.attached_deposit(cost).build());
+        contract.mint_tokens(accounts(3), 10.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 10);
+    }
+
+    #[test]
+    fn test_mint_batch_registers_receivers_from_pool() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        let cost = contract.storage_balance_bounds().min.0;
+
+        testing_env!(context.attached_deposit(cost * 2).build());
+        contract.deposit_storage_pool();
+
+        testing_env!(context.attached_deposit(0).build());
+        contract.mint_tokens_batch(vec![(accounts(3), 10.into()), (accounts(4), 20.into())], None);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 10);
+        assert_eq!(contract.ft_balance_of(accounts(4)).0, 20);
+        assert_eq!(contract.get_storage_pool(accounts(1)).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Must mint to between 1 and 100 accounts.")]
+    fn test_mint_batch_size_is_capped() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.mint_tokens_batch(vec![(accounts(1), 1.into()); MAX_BATCH_SIZE + 1], None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough deposit or storage pool to register the receiver.")]
+    fn test_mint_to_unregistered_without_deposit() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.mint_tokens(accounts(3), 10.into(), None);
+    }
+
+    #[test]
+    fn test_mint_and_burn_emit_events() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+
+        contract.mint_tokens(accounts(1), 10.into(), Some("payout".to_string()));
+        contract.burn_tokens(accounts(1), 4.into(), None);
+
+        let logs = get_logs();
+        assert_eq!(
+            logs[logs.len() - 2],
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_mint","data":[{"owner_id":"bob","amount":"10","memo":"payout"}]}"#
+        );
+        assert_eq!(
+            logs[logs.len() - 1],
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_burn","data":[{"owner_id":"bob","amount":"4"}]}"#
+        );
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only a minter can call this function.")]
+    fn test_removed_minter_cannot_mint() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.add_minter(accounts(2));
+        contract.remove_minter(accounts(2));
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.mint_tokens(accounts(1), 10.into(), None);
+    }
+
+    #[test]
+    fn test_set_metadata() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+
+        let mut metadata = contract_metadata();
+        metadata.icon = Some("data:image/svg+xml,%3Csvg%3E%3C/svg%3E".to_string());
+        contract.set_metadata(metadata);
+        assert_eq!(contract.ft_metadata().symbol, "TEST");
+        assert!(contract.ft_metadata().icon.is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this function.")]
+    fn test_set_metadata_owner_only() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.set_metadata(contract_metadata());
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is frozen.")]
+    fn test_frozen_token_cannot_be_transferred() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.freeze();
+        assert!(contract.is_frozen());
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(2), 10.into(), None);
+    }
+
+    #[test]
+    fn test_frozen_token_can_be_burned() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.freeze();
+        assert_eq!(
+            get_logs().last().unwrap(),
+            r#"EVENT_JSON:{"event":"ft_freeze","standard":"x-outcome-token","version":"1.0.0"}"#
+        );
+
+        contract.burn_tokens(accounts(1), 10.into(), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY - 10);
+
+        contract.unfreeze();
+        assert!(!contract.is_frozen());
+    }
+
+    #[test]
+    fn test_two_step_ownership_transfer() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.propose_owner(Some(accounts(2)));
+        assert_eq!(contract.get_owner(), Some(accounts(1)));
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.accept_ownership();
+        assert_eq!(contract.get_owner(), Some(accounts(2)));
+        assert_eq!(contract.get_proposed_owner(), None);
+        assert_eq!(contract.get_minters(), vec![accounts(2)]);
+        assert_eq!(
+            get_logs().last().unwrap(),
+            r#"EVENT_JSON:{"data":[{"new_owner":"charlie","old_owner":"bob"}],"event":"ownership_transferred","standard":"x-outcome-token","version":"1.0.0"}"#
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the proposed owner can accept ownership.")]
+    fn test_accept_ownership_not_proposed() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.propose_owner(Some(accounts(2)));
+
+        testing_env!(context.predecessor_account_id(accounts(3)).build());
+        contract.accept_ownership();
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this function.")]
+    fn test_renounced_ownership() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.renounce_ownership();
+        assert_eq!(contract.get_owner(), None);
+
+        contract.add_minter(accounts(2));
+    }
+
+    #[test]
+    fn test_balance_checkpoints() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_index(10).build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.enable_checkpoints();
+        testing_env!(context.attached_deposit(10u128.pow(23)).build());
+        contract.fund_checkpoint_storage();
+
+        testing_env!(context.block_index(12).attached_deposit(0).build());
+        contract.burn_tokens(accounts(1), 10.into(), None);
+        testing_env!(context.block_index(15).build());
+        contract.mint_tokens(accounts(1), 30.into(), None);
+
+        assert_eq!(contract.ft_balance_of_at(accounts(1), 10.into()).0, TOTAL_SUPPLY);
+        assert_eq!(contract.ft_balance_of_at(accounts(1), 14.into()).0, TOTAL_SUPPLY - 10);
+        assert_eq!(contract.ft_balance_of_at(accounts(1), 15.into()).0, TOTAL_SUPPLY + 20);
+        assert_eq!(contract.ft_total_supply_at(11.into()).0, TOTAL_SUPPLY);
+        assert_eq!(contract.ft_total_supply_at(20.into()).0, TOTAL_SUPPLY + 20);
+        assert_eq!(contract.ft_balance_of_at(accounts(2), 12.into()).0, 0);
+    }
+
+    #[test]
+    fn test_checkpoint_storage_is_charged() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_index(10).build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.enable_checkpoints();
+        testing_env!(context.attached_deposit(10u128.pow(23)).build());
+        contract.fund_checkpoint_storage();
+
+        testing_env!(context.block_index(12).attached_deposit(0).build());
+        contract.burn_tokens(accounts(1), 10.into(), None);
+        let left = contract.get_checkpoint_storage().0;
+        assert!(left < 10u128.pow(23));
+
+        // A second change in the same block overwrites the last checkpoint.
+        contract.burn_tokens(accounts(1), 10.into(), None);
+        assert_eq!(contract.get_checkpoint_storage().0, left);
+    }
+
+    #[test]
+    fn test_transfer_with_empty_checkpoint_fund() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_index(10).build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.enable_checkpoints();
+        let cost = contract.storage_balance_bounds().min.0;
+        testing_env!(context.attached_deposit(cost).build());
+        contract.mint_tokens(accounts(2), 1.into(), None);
+
+        testing_env!(context.block_index(12).attached_deposit(1).build());
+        contract.ft_transfer(accounts(2), 10.into(), None);
+        assert_eq!(contract.ft_balance_of_at(accounts(2), 12.into()).0, 11);
+        assert_eq!(contract.get_checkpoint_storage().0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Checkpoints only go back to block 10.")]
+    fn test_balance_before_checkpoints() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.block_index(10).build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        contract.enable_checkpoints();
+
+        contract.ft_balance_of_at(accounts(1), 9.into());
+    }
+
+    #[test]
+    fn test_redeem_burns_and_refunds_on_failure() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(0).into(), TOTAL_SUPPLY.into());
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .build());
+        contract.storage_deposit(None, None);
+        testing_env!(context.predecessor_account_id(accounts(0)).build());
+        contract.mint_tokens(accounts(1), 10.into(), None);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.ft_redeem(4.into());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 6);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 6);
+
+        testing_env!(context.predecessor_account_id(accounts(0)).attached_deposit(0).build());
+        assert!(!contract.on_redeem(accounts(1), 4.into(), Err(PromiseError::Failed)));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 10);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 10);
+    }
+
+    #[test]
+    fn test_transfer_batch() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        let cost = contract.storage_balance_bounds().min.0;
+        testing_env!(context.attached_deposit(cost * 2).build());
+        contract.mint_tokens_batch(vec![(accounts(2), 1.into()), (accounts(3), 1.into())], None);
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer_batch(vec![(accounts(2), 10.into()), (accounts(3), 20.into())], None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY - 30);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 11);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 21);
+        assert_eq!(
+            get_logs().last().unwrap(),
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_transfer","data":[{"old_owner_id":"bob","new_owner_id":"charlie","amount":"10"},{"old_owner_id":"bob","new_owner_id":"danny","amount":"20"}]}"#
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The account danny is not registered")]
+    fn test_transfer_batch_all_or_nothing() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        let cost = contract.storage_balance_bounds().min.0;
+        testing_env!(context.attached_deposit(cost).build());
+        contract.mint_tokens(accounts(2), 1.into(), None);
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer_batch(vec![(accounts(2), 10.into()), (accounts(3), 20.into())], None);
+    }
+
+    #[test]
+    fn test_vesting_with_cliff() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        let cost = contract.storage_balance_bounds().min.0;
+        testing_env!(context.attached_deposit(cost).build());
+        contract.mint_tokens(accounts(2), 1.into(), None);
+
+        testing_env!(context.attached_deposit(1).block_timestamp(0).build());
+        let id = contract.create_vesting(accounts(2), 100.into(), 0.into(), 25.into(), 100.into());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY - 100);
+        assert_eq!(contract.ft_balance_of(accounts(0)).0, 100);
+
+        testing_env!(context.block_timestamp(20).build());
+        assert_eq!(contract.get_claimable_vested(id).0, 0);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).block_timestamp(40).build());
+        assert_eq!(contract.claim_vested(id).0, 40);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 41);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(50).build());
+        assert_eq!(contract.revoke_vesting(id).0, 50);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY - 50);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).block_timestamp(200).build());
+        assert_eq!(contract.claim_vested(id).0, 10);
+        assert_eq!(contract.ft_balance_of(accounts(0)).0, 0);
+        assert_eq!(contract.get_vestings_of(accounts(2)).len(), 1);
+    }
+
+    #[test]
+    fn test_claim_between_revoke_and_end() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        let cost = contract.storage_balance_bounds().min.0;
+        testing_env!(context.attached_deposit(cost).build());
+        contract.mint_tokens(accounts(2), 1.into(), None);
+
+        testing_env!(context.attached_deposit(1).block_timestamp(0).build());
+        let id = contract.create_vesting(accounts(2), 100.into(), 0.into(), 0.into(), 100.into());
+
+        testing_env!(context.predecessor_account_id(accounts(2)).block_timestamp(40).build());
+        assert_eq!(contract.claim_vested(id).0, 40);
+
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(50).build());
+        assert_eq!(contract.revoke_vesting(id).0, 50);
+
+        testing_env!(context.predecessor_account_id(accounts(2)).block_timestamp(60).build());
+        assert_eq!(contract.get_claimable_vested(id).0, 10);
+        assert_eq!(contract.claim_vested(id).0, 10);
+        assert_eq!(contract.get_claimable_vested(id).0, 0);
+    }
+
+    #[test]
+    fn test_vesting_large_amount() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let supply = 10u128.pow(36);
+        let mut contract = Contract::new_default_meta(accounts(1).into(), supply.into());
+        let cost = contract.storage_balance_bounds().min.0;
+        testing_env!(context.attached_deposit(cost).build());
+        contract.mint_tokens(accounts(2), 1.into(), None);
+
+        // Four years, in nanoseconds.
+        let duration = 4 * 365 * 24 * 60 * 60 * 1_000_000_000;
+        testing_env!(context.attached_deposit(1).block_timestamp(0).build());
+        let id = contract.create_vesting(
+            accounts(2),
+            supply.into(),
+            0.into(),
+            0.into(),
+            duration.into(),
+        );
+
+        testing_env!(context.block_timestamp(duration / 2).build());
+        assert_eq!(contract.get_claimable_vested(id).0, supply / 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Amount must be nonzero.")]
+    fn test_vesting_amount_nonzero() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        let cost = contract.storage_balance_bounds().min.0;
+        testing_env!(context.attached_deposit(cost).build());
+        contract.mint_tokens(accounts(2), 1.into(), None);
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.create_vesting(accounts(2), 0.into(), 0.into(), 0.into(), 100.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "Tokens cannot be sent to the token contract itself.")]
+    fn test_transfer_to_escrow() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        let cost = contract.storage_balance_bounds().min.0;
+        testing_env!(context.attached_deposit(cost).build());
+        contract.mint_tokens(accounts(2), 1.into(), None);
+        testing_env!(context.attached_deposit(1).build());
+        contract.create_vesting(accounts(2), 100.into(), 0.into(), 0.into(), 100.into());
+
+        contract.ft_transfer(accounts(0), 10.into(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the beneficiary can claim.")]
+    fn test_claim_vested_beneficiary_only() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+        let cost = contract.storage_balance_bounds().min.0;
+        testing_env!(context.attached_deposit(cost).build());
+        contract.mint_tokens(accounts(2), 1.into(), None);
+
+        testing_env!(context.attached_deposit(1).block_timestamp(0).build());
+        let id = contract.create_vesting(accounts(2), 100.into(), 0.into(), 0.into(), 100.into());
+
+        testing_env!(context.attached_deposit(0).block_timestamp(50).build());
+        contract.claim_vested(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the owner can call this function.")]
+    fn test_add_minter_owner_only() {
+        let mut context = get_context(accounts(1));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(1).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.add_minter(accounts(2));
+    }
 }
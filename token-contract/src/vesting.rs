@@ -0,0 +1,205 @@
+//! Linear vesting with an optional cliff.
+//!
+//! Tokens put into a schedule are escrowed on the contract's own account,
+//! which cannot receive tokens any other way. Nothing vests before `start + cliff`; from then on the vested amount grows
+//! linearly until everything has vested at `start + duration`.
+use super::*;
+use near_sdk::serde::Serialize;
+
+mod u256 {
+    // The macro expands to code clippy objects to.
+    #![allow(clippy::all)]
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct VestingSchedule {
+    id: U64,
+    beneficiary: AccountId,
+    total: U128,
+    claimed: U128,
+    /// Timestamp vesting starts at, in nanoseconds.
+    start: U64,
+    /// Time after `start` before anything vests, in nanoseconds.
+    cliff: U64,
+    /// Time after `start` everything has vested at, in nanoseconds.
+    duration: U64,
+    revoked: bool,
+}
+
+impl VestingSchedule {
+    fn vested_at(&self, timestamp: u64) -> Balance {
+        let elapsed = timestamp.saturating_sub(self.start.0);
+        if self.revoked || elapsed >= self.duration.0 {
+            // Revoking cuts `total` down to what had vested by then.
+            self.total.0
+        } else if elapsed < self.cliff.0 {
+            0
+        } else {
+            let vested = u256::U256::from(self.total.0) * u256::U256::from(elapsed)
+                / u256::U256::from(self.duration.0);
+            vested.as_u128()
+        }
+    }
+
+    fn claimable_at(&self, timestamp: u64) -> Balance {
+        self.vested_at(timestamp) - self.claimed.0
+    }
+}
+
+impl Contract {
+    /// Keeps anything but escrowed tokens off the escrow account.
+    pub(crate) fn assert_not_escrow(account_id: &AccountId) {
+        require!(
+            account_id != &env::current_account_id(),
+            "Tokens cannot be sent to the token contract itself."
+        );
+    }
+
+    fn vesting_schedule(&self, schedule_id: u64) -> VestingSchedule {
+        self.vesting_schedules
+            .get(&schedule_id)
+            .unwrap_or_else(|| env::panic_str("Vesting schedule does not exist."))
+    }
+
+    /// Moves `amount` between `sender_id` and `receiver_id`, one of which is
+    /// the escrow account.
+    fn escrow_transfer(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: &str,
+    ) {
+        self.with_checkpoints(&[sender_id, receiver_id], |this| {
+            this.token
+                .internal_transfer(sender_id, receiver_id, amount, Some(memo.to_string()))
+        });
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Escrows `amount` of the owner's tokens, vesting to `beneficiary` from
+    /// `start` on. Requires 1 yoctoNEAR. Owner only.
+    #[payable]
+    pub fn create_vesting(
+        &mut self,
+        beneficiary: AccountId,
+        amount: U128,
+        start: U64,
+        cliff: U64,
+        duration: U64,
+    ) -> U64 {
+        assert_one_yocto();
+        self.assert_owner_calling();
+        require!(amount.0 > 0, "Amount must be nonzero.");
+        require!(duration.0 > 0, "Duration must be nonzero.");
+        require!(
+            cliff.0 <= duration.0,
+            "Cliff cannot be longer than the duration."
+        );
+        require!(
+            self.token.accounts.contains_key(&beneficiary),
+            "Beneficiary must be registered."
+        );
+
+        let escrow = env::current_account_id();
+        if !self.token.accounts.contains_key(&escrow) {
+            self.token.internal_register_account(&escrow);
+        }
+        let owner_id = env::predecessor_account_id();
+        self.escrow_transfer(&owner_id, &escrow, amount.0, "vesting");
+
+        let id = self.next_vesting_id;
+        self.next_vesting_id += 1;
+        let schedule = VestingSchedule {
+            id: id.into(),
+            beneficiary: beneficiary.clone(),
+            total: amount,
+            claimed: 0.into(),
+            start,
+            cliff,
+            duration,
+            revoked: false,
+        };
+        self.vesting_schedules.insert(&id, &schedule);
+
+        let mut ids = self.account_vestings.get(&beneficiary).unwrap_or_default();
+        ids.push(id);
+        self.account_vestings.insert(&beneficiary, &ids);
+
+        id.into()
+    }
+
+    /// Releases everything vested so far to the beneficiary. Callable by the
+    /// beneficiary only.
+    pub fn claim_vested(&mut self, schedule_id: U64) -> U128 {
+        let mut schedule = self.vesting_schedule(schedule_id.0);
+        require!(
+            env::predecessor_account_id() == schedule.beneficiary,
+            "Only the beneficiary can claim."
+        );
+        let amount = schedule.claimable_at(env::block_timestamp());
+        require!(amount > 0, "Nothing has vested yet.");
+
+        schedule.claimed.0 += amount;
+        self.vesting_schedules.insert(&schedule_id.0, &schedule);
+        self.escrow_transfer(
+            &env::current_account_id(),
+            &schedule.beneficiary,
+            amount,
+            "vesting claim",
+        );
+
+        amount.into()
+    }
+
+    /// Stops a schedule and returns what has not vested yet to the owner.
+    /// Whatever has vested stays claimable. Owner only.
+    pub fn revoke_vesting(&mut self, schedule_id: U64) -> U128 {
+        self.assert_owner_calling();
+        let mut schedule = self.vesting_schedule(schedule_id.0);
+        require!(!schedule.revoked, "Vesting schedule is already revoked.");
+
+        let vested = schedule.vested_at(env::block_timestamp());
+        let unvested = schedule.total.0 - vested;
+        schedule.total = vested.into();
+        schedule.revoked = true;
+        self.vesting_schedules.insert(&schedule_id.0, &schedule);
+
+        if unvested > 0 {
+            self.escrow_transfer(
+                &env::current_account_id(),
+                &env::predecessor_account_id(),
+                unvested,
+                "vesting revoked",
+            );
+        }
+
+        unvested.into()
+    }
+
+    pub fn get_vesting(&self, schedule_id: U64) -> Option<VestingSchedule> {
+        self.vesting_schedules.get(&schedule_id.0)
+    }
+
+    pub fn get_vestings_of(&self, account_id: AccountId) -> Vec<VestingSchedule> {
+        self.account_vestings
+            .get(&account_id)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|id| self.vesting_schedules.get(&id))
+            .collect()
+    }
+
+    /// How much `schedule_id` would release if claimed now.
+    pub fn get_claimable_vested(&self, schedule_id: U64) -> U128 {
+        self.vesting_schedule(schedule_id.0)
+            .claimable_at(env::block_timestamp())
+            .into()
+    }
+}